@@ -1,6 +1,7 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![allow(static_mut_refs)]
 use core::fmt::Debug;
+use extended_vft_client::vft::io as vft_io;
 use extended_vmt_client::vmt::io as vmt_io;
 use gstd::{ext, format, msg, exec};
 use sails_rs::{
@@ -11,6 +12,9 @@ use sails_rs::{
 const ZERO_ID: ActorId = ActorId::zero();
 const NFT_COUNT: U256 = U256::one();
 const BASIS_POINTS: u16 = 10000; // Para porcentajes con precisión (100% = 10000)
+const AUCTION_EXTENSION_WINDOW: u64 = 5 * 60 * 1000; // Anti-sniping: 5 minutos (timestamps en ms)
+const MAX_QUERY_LIMIT: u32 = 100; // Máximo de resultados por página en consultas paginadas
+const MAX_BENEFICIARIES: usize = 10; // Máximo de beneficiarios de regalías por evento
 
 /// Estructura principal de almacenamiento del contrato
 #[derive(Default, Clone)]
@@ -37,9 +41,556 @@ pub struct Storage {
     
     // Marketplace - Listados activos
     listings: HashMap<U256, Listing>, // ticket_id -> Listing
+    event_listings: HashMap<U256, HashSet<U256>>, // event_id -> {ticket_id} listados
+    seller_listings: HashMap<ActorId, HashSet<U256>>, // vendedor -> {ticket_id} listados
+    
+    // Marketplace - Paquetes de tickets vendidos juntos
+    bundle_id_counter: U256,
+    bundles: HashMap<U256, Bundle>, // bundle_id -> Bundle
+    ticket_bundles: HashMap<U256, U256>, // ticket_id -> bundle_id
+    
+    // Marketplace - Ofertas sobre tickets (fondos en escrow)
+    offer_id_counter: U256,
+    offers: HashMap<U256, Offer>, // offer_id -> Offer
+    ticket_offers: HashMap<U256, HashSet<U256>>, // ticket_id -> {offer_id}
+    bidder_offers: HashMap<ActorId, HashSet<U256>>, // ofertante -> {offer_id}
+    
+    // Marketplace - Subastas inglesas activas
+    auctions: HashMap<U256, Auction>, // ticket_id -> Auction
+    
+    // Saldos reclamables (pull-payment): (cuenta, moneda) -> saldo
+    balances: HashMap<(ActorId, Option<ActorId>), U256>,
+    
+    // Recaudación de venta primaria en escrow hasta el inicio del evento: event_id -> monto
+    primary_escrow: HashMap<U256, U256>,
+    primary_released: HashSet<U256>, // Eventos cuya recaudación ya se liberó al organizador
+    
+    // Comisiones de plataforma
+    platform_revenue_total: HashMap<Option<ActorId>, U256>, // moneda -> total acumulado histórico
+    platform_revenue_by_event: HashMap<U256, U256>, // event_id -> total acumulado (moneda del evento)
+    platform_accrued: HashMap<Option<ActorId>, U256>, // moneda -> comisiones pendientes de retiro
+    
+    // Estadísticas financieras mantenidas incrementalmente
+    event_financials: HashMap<U256, EventFinancials>, // event_id -> EventFinancials
+    holdings: HashMap<(U256, ActorId), u32>, // (event_id, poseedor) -> tickets en posesión
+    
+    // Asientos numerados asignados: un asiento pertenece a lo sumo a un ticket
+    seat_assignments: HashMap<(U256, Seat), U256>, // (event_id, asiento) -> ticket_id
+    
+    // Cuotas por billetera
+    primary_purchases: HashMap<(U256, ActorId), u32>, // (event_id, comprador) -> tickets obtenidos en venta primaria
+    resales: HashMap<(U256, ActorId), u32>, // (event_id, vendedor) -> reventas concretadas
+    
+    // Fan exchange: filas FIFO con el precio de lista en escrow
+    waitlists: HashMap<(U256, Option<String>), Vec<WaitlistEntry>>, // (event_id, zona) -> fila
     
     // Reentrancy guard
     locked: bool,
+    pending_tickets: HashSet<U256>, // Tickets cuyo estado puede restaurar la operación en curso
+}
+
+impl Storage {
+    /// Acredita `amount` al saldo reclamable de una cuenta en la moneda indicada
+    fn credit(&mut self, account: ActorId, currency: Option<ActorId>, amount: U256) {
+        if amount.is_zero() {
+            return;
+        }
+        *self.balances.entry((account, currency)).or_default() += amount;
+    }
+    
+    /// Devuelve VARA a una cuenta; si el envío es rechazado, el monto queda en su saldo reclamable
+    async fn refund_value(&mut self, to: ActorId, amount: u128) {
+        if !send_value(to, amount).await {
+            self.credit(to, None, U256::from(amount));
+        }
+    }
+    
+    /// Devuelve a `payer` el pago de una operación que no se completó: el VARA adjunto
+    /// se reenvía y lo cobrado en token (`collected`) queda en su saldo reclamable
+    async fn return_payment(&mut self, payer: ActorId, currency: Option<ActorId>, collected: U256, attached: u128) {
+        if currency.is_some() {
+            self.credit(payer, currency, collected);
+        }
+        self.refund_value(payer, attached).await;
+    }
+    
+    /// Rechaza una operación síncrona sobre un ticket mientras otra operación en curso
+    /// espera una respuesta y puede restaurar su estado (listado, paquete, subasta o NFT)
+    fn check_not_pending(&self, ticket_id: &U256) -> Result<(), TicketError> {
+        if self.pending_tickets.contains(ticket_id) {
+            return Err(TicketError::OperationPending);
+        }
+        Ok(())
+    }
+    
+    /// Obtiene (o inicializa) las estadísticas financieras de un evento
+    fn financials_mut(&mut self, event_id: U256) -> &mut EventFinancials {
+        self.event_financials
+            .entry(event_id)
+            .or_insert_with(|| EventFinancials {
+                event_id,
+                ..Default::default()
+            })
+    }
+    
+    /// Actualiza la tenencia de tickets de un evento cuando un ticket cambia de manos
+    /// (`from` = None en minteo, `to` = None en quema)
+    fn move_holding(&mut self, event_id: U256, from: Option<ActorId>, to: Option<ActorId>) {
+        if let Some(from) = from {
+            if let Some(count) = self.holdings.get_mut(&(event_id, from)) {
+                *count -= 1;
+                if *count == 0 {
+                    self.holdings.remove(&(event_id, from));
+                    self.financials_mut(event_id).unique_holders -= 1;
+                }
+            }
+        }
+        if let Some(to) = to {
+            let count = self.holdings.entry((event_id, to)).or_default();
+            *count += 1;
+            if *count == 1 {
+                self.financials_mut(event_id).unique_holders += 1;
+            }
+        }
+    }
+    
+    /// Registra una reventa en las estadísticas del evento y en la cuota del vendedor
+    fn record_resale(&mut self, event_id: U256, seller: ActorId, price: U256, organizer_share: U256) {
+        let financials = self.financials_mut(event_id);
+        financials.secondary_volume += price;
+        financials.resale_count += 1;
+        financials.organizer_royalties += organizer_share;
+        *self.resales.entry((event_id, seller)).or_default() += 1;
+    }
+    
+    /// Quita una entrada de la lista de espera de (evento, zona)
+    fn remove_waitlist_entry(&mut self, key: &(U256, Option<String>), position: usize) -> WaitlistEntry {
+        let queue = self.waitlists.get_mut(key).expect("Waitlist not found");
+        let entry = queue.remove(position);
+        if queue.is_empty() {
+            self.waitlists.remove(key);
+        }
+        entry
+    }
+    
+    /// Valida los límites por billetera de una compra en reventa:
+    /// tenencia del comprador y reventas de cada vendedor.
+    /// `purchases` contiene (event_id, vendedor) por cada ticket comprado.
+    fn check_purchase_limits(&self, buyer: ActorId, purchases: &[(U256, ActorId)]) -> Result<(), TicketError> {
+        for (i, key) in purchases.iter().enumerate() {
+            // Cada par (evento, vendedor) se valida una sola vez con el total del lote
+            if purchases[..i].contains(key) {
+                continue;
+            }
+            let (event_id, seller) = *key;
+            let limits = &self.events.get(&event_id).ok_or(TicketError::EventNotFound)?.wallet_limits;
+            
+            let incoming = purchases.iter().filter(|(id, _)| *id == event_id).count() as u64;
+            let held = self.holdings.get(&(event_id, buyer)).copied().unwrap_or_default();
+            limits.check_holding(held, incoming)?;
+            
+            let sold = purchases.iter().filter(|p| *p == key).count() as u32;
+            let resold = self.resales.get(&(event_id, seller)).copied().unwrap_or_default();
+            limits.check_resale(resold + sold - 1)?;
+        }
+        Ok(())
+    }
+    
+    /// Liquida una reventa cuyo pago ya está en poder del contrato:
+    /// actualiza el propietario, acredita las participaciones y actualiza las estadísticas.
+    fn settle_resale(&mut self, ticket_id: U256, buyer: ActorId, price: U256) -> SaleSplit {
+        let ticket = self.tickets.get_mut(&ticket_id).expect("Ticket not found");
+        let seller = ticket.current_owner;
+        let event_id = ticket.event_id;
+        ticket.current_owner = buyer;
+        
+        let event_config = self.events.get(&event_id).expect("Event not found");
+        let split = event_config.commission_config.split(price);
+        let organizer = event_config.organizer;
+        let currency = event_config.payment_token;
+        
+        self.credit(seller, currency, split.seller_share);
+        self.credit(organizer, currency, split.organizer_share);
+        for (beneficiary, amount) in split.beneficiary_shares.iter() {
+            self.credit(*beneficiary, currency, *amount);
+        }
+        self.accrue_platform_fee(event_id, currency, split.platform_share);
+        
+        self.record_resale(event_id, seller, price, split.organizer_share);
+        self.move_holding(event_id, Some(seller), Some(buyer));
+        
+        split
+    }
+    
+    /// Registra una oferta y sus índices por ticket y por ofertante
+    fn insert_offer(&mut self, offer: Offer) {
+        self.ticket_offers
+            .entry(offer.ticket_id)
+            .or_default()
+            .insert(offer.offer_id);
+        self.bidder_offers
+            .entry(offer.bidder)
+            .or_default()
+            .insert(offer.offer_id);
+        self.offers.insert(offer.offer_id, offer);
+    }
+    
+    /// Busca una oferta vigente de `bidder` por `ticket_id` al precio y moneda exactos
+    fn find_offer(
+        &self,
+        ticket_id: U256,
+        bidder: ActorId,
+        price: U256,
+        currency: Option<ActorId>,
+        current_time: u64,
+    ) -> Option<U256> {
+        self.ticket_offers.get(&ticket_id)?.iter().copied().find(|offer_id| {
+            self.offers.get(offer_id).is_some_and(|offer| {
+                offer.bidder == bidder
+                    && offer.price == price
+                    && offer.currency == currency
+                    && current_time <= offer.expires_at
+            })
+        })
+    }
+    
+    /// Remueve una oferta y sus índices
+    fn remove_offer(&mut self, offer_id: &U256) -> Option<Offer> {
+        let offer = self.offers.remove(offer_id)?;
+        if let Some(ids) = self.ticket_offers.get_mut(&offer.ticket_id) {
+            ids.remove(offer_id);
+            if ids.is_empty() {
+                self.ticket_offers.remove(&offer.ticket_id);
+            }
+        }
+        if let Some(ids) = self.bidder_offers.get_mut(&offer.bidder) {
+            ids.remove(offer_id);
+            if ids.is_empty() {
+                self.bidder_offers.remove(&offer.bidder);
+            }
+        }
+        Some(offer)
+    }
+    
+    /// Agrega un listado activo manteniendo índices y estadísticas del evento
+    fn insert_listing(&mut self, listing: Listing) {
+        let event_id = listing.event_id;
+        self.event_listings
+            .entry(event_id)
+            .or_default()
+            .insert(listing.ticket_id);
+        self.seller_listings
+            .entry(listing.seller)
+            .or_default()
+            .insert(listing.ticket_id);
+        self.listings.insert(listing.ticket_id, listing);
+        self.financials_mut(event_id).active_listings += 1;
+    }
+    
+    /// Remueve un listado activo manteniendo índices y estadísticas del evento
+    fn remove_listing(&mut self, ticket_id: &U256) -> Option<Listing> {
+        let listing = self.listings.remove(ticket_id)?;
+        let event_id = listing.event_id;
+        if let Some(ids) = self.event_listings.get_mut(&event_id) {
+            ids.remove(ticket_id);
+            if ids.is_empty() {
+                self.event_listings.remove(&event_id);
+            }
+        }
+        if let Some(ids) = self.seller_listings.get_mut(&listing.seller) {
+            ids.remove(ticket_id);
+            if ids.is_empty() {
+                self.seller_listings.remove(&listing.seller);
+            }
+        }
+        self.financials_mut(event_id).active_listings -= 1;
+        Some(listing)
+    }
+    
+    /// Retira el listado, el paquete o la subasta de un ticket cuyo estado cambió (si los tiene).
+    /// Devuelve los eventos a emitir por el servicio que lo invoca.
+    fn delist_ticket(&mut self, ticket_id: U256, reason: ListingCancelReason) -> Vec<Event> {
+        let mut events = Vec::new();
+        if let Some(listing) = self.remove_listing(&ticket_id) {
+            events.push(Event::ListingCancelled {
+                ticket_id,
+                event_id: listing.event_id,
+                seller: listing.seller,
+                reason,
+            });
+        }
+        if let Some(bundle) = self.remove_ticket_bundle(&ticket_id) {
+            events.push(Event::BundleCancelled {
+                bundle_id: bundle.bundle_id,
+                event_id: bundle.event_id,
+                seller: bundle.seller,
+                reason,
+            });
+        }
+        if let Some(auction) = self.auctions.remove(&ticket_id) {
+            // La puja más alta vuelve al saldo reclamable de su ofertante
+            if let Some(bidder) = auction.highest_bidder {
+                self.credit(bidder, auction.currency, auction.highest_bid);
+            }
+            events.push(Event::AuctionCancelled {
+                ticket_id,
+                event_id: auction.event_id,
+                seller: auction.seller,
+            });
+        }
+        events
+    }
+    
+    /// Retira todos los listados, paquetes y subastas de un evento
+    fn delist_event(&mut self, event_id: U256, reason: ListingCancelReason) -> Vec<Event> {
+        let mut ticket_ids: Vec<U256> = self
+            .event_listings
+            .get(&event_id)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default();
+        ticket_ids.extend(
+            self.bundles
+                .values()
+                .filter(|bundle| bundle.event_id == event_id)
+                .filter_map(|bundle| bundle.ticket_ids.first().copied()),
+        );
+        ticket_ids.extend(
+            self.auctions
+                .values()
+                .filter(|auction| auction.event_id == event_id)
+                .map(|auction| auction.ticket_id),
+        );
+        ticket_ids
+            .into_iter()
+            .flat_map(|ticket_id| self.delist_ticket(ticket_id, reason))
+            .collect()
+    }
+    
+    /// Retira de nuevo la venta de un ticket que una operación fallida acaba de restaurar
+    /// si, mientras se esperaba la respuesta, su evento se canceló o se desactivó
+    fn delist_if_closed(&mut self, ticket_id: U256, event_id: U256) -> Vec<Event> {
+        let reason = match self.events.get(&event_id) {
+            Some(event_config) if event_config.cancelled => ListingCancelReason::EventCancelled,
+            Some(event_config) if !event_config.active => ListingCancelReason::EventDisabled,
+            _ => return Vec::new(),
+        };
+        self.delist_ticket(ticket_id, reason)
+    }
+    
+    /// Valida que `buyer` pueda comprar un listado en este momento.
+    /// Devuelve el listado, su precio vigente y la moneda del evento.
+    fn check_purchase(
+        &self,
+        buyer: ActorId,
+        ticket_id: U256,
+        current_time: u64,
+    ) -> Result<(Listing, U256, Option<ActorId>), TicketError> {
+        // Obtener listado
+        let listing = self.listings.get(&ticket_id).ok_or(TicketError::ListingNotFound)?;
+        
+        // Validar que el comprador no sea el vendedor
+        if buyer == listing.seller {
+            return Err(TicketError::InvalidInput);
+        }
+        
+        // Un listado reservado solo lo puede comprar su destinatario
+        if listing.reserved_for.is_some_and(|reserved_for| reserved_for != buyer) {
+            return Err(TicketError::ListingReserved);
+        }
+        
+        // Validar que el ticket todavía es válido
+        let ticket = self.tickets.get(&ticket_id).ok_or(TicketError::TicketNotFound)?;
+        
+        // Validar que no esté usado
+        if ticket.used {
+            return Err(TicketError::TicketAlreadyUsed);
+        }
+        
+        // Validar propiedad
+        if ticket.current_owner != listing.seller {
+            return Err(TicketError::TicketNotOwned);
+        }
+        
+        // Validar evento
+        let event_config = self.events.get(&ticket.event_id).ok_or(TicketError::EventNotFound)?;
+        
+        // Validar que el evento no esté cancelado (transferencias congeladas)
+        if event_config.cancelled {
+            return Err(TicketError::EventCancelled);
+        }
+        
+        // Un evento desactivado no admite ventas
+        if !event_config.active {
+            return Err(TicketError::EventNotActive);
+        }
+        
+        // Validar que el listado no haya vencido
+        if listing.is_expired(event_config, current_time) {
+            return Err(TicketError::ListingExpired);
+        }
+        
+        // Precio vigente (en subasta holandesa depende del momento de compra)
+        let price = listing.current_price(current_time);
+        
+        // Validar precio vigente vs límites de reventa actuales
+        event_config.check_resale_price(price, ticket)?;
+        
+        Ok((listing.clone(), price, event_config.payment_token))
+    }
+    
+    /// Indica si el ticket ya está a la venta (listado, en subasta o en un paquete)
+    fn is_on_sale(&self, ticket_id: &U256) -> bool {
+        self.listings.contains_key(ticket_id)
+            || self.auctions.contains_key(ticket_id)
+            || self.ticket_bundles.contains_key(ticket_id)
+    }
+    
+    /// Registra un paquete y la pertenencia de sus tickets
+    fn insert_bundle(&mut self, bundle: Bundle) {
+        for ticket_id in bundle.ticket_ids.iter() {
+            self.ticket_bundles.insert(*ticket_id, bundle.bundle_id);
+        }
+        self.bundles.insert(bundle.bundle_id, bundle);
+    }
+    
+    /// Remueve un paquete y la pertenencia de sus tickets
+    fn remove_bundle(&mut self, bundle_id: &U256) -> Option<Bundle> {
+        let bundle = self.bundles.remove(bundle_id)?;
+        for ticket_id in bundle.ticket_ids.iter() {
+            self.ticket_bundles.remove(ticket_id);
+        }
+        Some(bundle)
+    }
+    
+    /// Remueve el paquete que contiene un ticket (si lo hay)
+    fn remove_ticket_bundle(&mut self, ticket_id: &U256) -> Option<Bundle> {
+        let bundle_id = *self.ticket_bundles.get(ticket_id)?;
+        self.remove_bundle(&bundle_id)
+    }
+    
+    /// Listados activos de un conjunto de IDs del índice (vacío si no hay índice)
+    fn listings_in(&self, ids: Option<&HashSet<U256>>) -> Vec<&Listing> {
+        ids.map(|ids| ids.iter().filter_map(|id| self.listings.get(id)).collect())
+            .unwrap_or_default()
+    }
+    
+    /// Precio vigente más bajo entre los listados activos de un evento. Se calcula al
+    /// consultar porque el precio de una subasta holandesa depende del momento.
+    fn floor_listing_price(&self, event_id: U256, current_time: u64) -> Option<U256> {
+        self.listings_in(self.event_listings.get(&event_id))
+            .into_iter()
+            .map(|listing| listing.current_price(current_time))
+            .min()
+    }
+    
+    /// Registra una comisión de plataforma cobrada en una venta del evento
+    fn accrue_platform_fee(&mut self, event_id: U256, currency: Option<ActorId>, amount: U256) {
+        if amount.is_zero() {
+            return;
+        }
+        *self.platform_revenue_total.entry(currency).or_default() += amount;
+        *self.platform_revenue_by_event.entry(event_id).or_default() += amount;
+        *self.platform_accrued.entry(currency).or_default() += amount;
+    }
+    
+    /// Crea un ticket para `buyer` por cada (zona, asiento) de `placements`
+    /// y los registra en el evento.
+    /// Devuelve los IDs creados y la zona y el asiento asignados a cada uno.
+    fn create_tickets(
+        &mut self,
+        event_id: U256,
+        buyer: ActorId,
+        placements: Vec<(Option<String>, Option<Seat>)>,
+        purchase_price: U256,
+        current_time: u64,
+    ) -> (Vec<U256>, Vec<Option<String>>, Vec<Option<Seat>>) {
+        let amount = placements.len();
+        let mut ticket_ids = Vec::new();
+        let mut zones_emitted = Vec::new();
+        let mut seats_emitted = Vec::new();
+        
+        for (zone, seat) in placements {
+            self.ticket_id_counter += U256::one();
+            let ticket_id = self.ticket_id_counter;
+            
+            zones_emitted.push(zone.clone());
+            
+            // Contar el ticket en su zona
+            if let Some(name) = zone.as_ref() {
+                if let Some(event_zone) = self
+                    .events
+                    .get_mut(&event_id)
+                    .and_then(|event_config| event_config.zones.iter_mut().find(|z| &z.name == name))
+                {
+                    event_zone.minted += U256::one();
+                }
+            }
+            
+            // Reservar el asiento asignado
+            if let Some(seat) = seat.as_ref() {
+                self.seat_assignments.insert((event_id, seat.clone()), ticket_id);
+            }
+            seats_emitted.push(seat.clone());
+            
+            let ticket = Ticket {
+                ticket_id,
+                event_id,
+                zone,
+                seat,
+                original_buyer: buyer,
+                current_owner: buyer,
+                used: false,
+                minted_at: current_time,
+                purchase_price,
+            };
+            
+            self.tickets.insert(ticket_id, ticket);
+            ticket_ids.push(ticket_id);
+            
+            // Agregar a la lista de tickets del evento
+            self.event_tickets
+                .entry(event_id)
+                .or_default()
+                .push(ticket_id);
+            
+            self.move_holding(event_id, None, Some(buyer));
+        }
+        *self.primary_purchases.entry((event_id, buyer)).or_default() += amount as u32;
+        
+        (ticket_ids, zones_emitted, seats_emitted)
+    }
+    
+    /// Deshace `create_tickets` cuando el minteo de los NFTs falla:
+    /// retira los tickets, libera sus asientos y descuenta zona, tenencia y cuota de compra
+    fn discard_tickets(&mut self, event_id: U256, buyer: ActorId, ticket_ids: &[U256]) {
+        for ticket_id in ticket_ids {
+            let Some(ticket) = self.tickets.remove(ticket_id) else {
+                continue;
+            };
+            
+            if let Some(name) = ticket.zone.as_ref() {
+                if let Some(event_zone) = self
+                    .events
+                    .get_mut(&event_id)
+                    .and_then(|event_config| event_config.zones.iter_mut().find(|z| &z.name == name))
+                {
+                    event_zone.minted -= U256::one();
+                }
+            }
+            
+            if let Some(seat) = ticket.seat {
+                self.seat_assignments.remove(&(event_id, seat));
+            }
+            
+            self.move_holding(event_id, Some(buyer), None);
+        }
+        if let Some(tickets) = self.event_tickets.get_mut(&event_id) {
+            tickets.retain(|id| !ticket_ids.contains(id));
+        }
+        if let Some(count) = self.primary_purchases.get_mut(&(event_id, buyer)) {
+            *count = count.saturating_sub(ticket_ids.len() as u32);
+        }
+    }
 }
 
 /// Configuración de un evento
@@ -56,40 +607,459 @@ pub struct EventConfig {
     pub resale_config: ResaleConfig,
     pub commission_config: CommissionConfig,
     pub active: bool, // Si el evento está activo
+    pub payment_token: Option<ActorId>, // Token extended-vft de liquidación (None = VARA nativo)
+    pub primary_price: Option<U256>, // Precio de venta primaria pública (None = venta pública deshabilitada)
+    pub zones: Vec<EventZone>, // Tabla de zonas (vacía = evento sin zonas numeradas)
+    pub cancelled: bool, // Evento cancelado (terminal): transferencias congeladas y reembolsos abiertos
+    pub wallet_limits: WalletLimits, // Cuotas por billetera contra acaparamiento
+    pub fan_exchange: bool, // Reventa a precio de lista asignada por lista de espera
 }
 
-/// Configuración de reventa
+impl EventConfig {
+    /// Valida que se pueda revender `ticket` (de este evento) a `price` en `current_time`
+    fn check_resale(&self, price: U256, ticket: &Ticket, current_time: u64) -> Result<(), TicketError> {
+        if self.cancelled {
+            return Err(TicketError::EventCancelled);
+        }
+        if !self.active {
+            return Err(TicketError::EventNotActive);
+        }
+        if !self.resale_config.enabled {
+            return Err(TicketError::ResaleDisabled);
+        }
+        self.check_resale_price(price, ticket)?;
+        if let Some(start_time) = self.resale_config.resale_start_time {
+            if current_time < start_time {
+                return Err(TicketError::ResaleWindowClosed);
+            }
+        }
+        if let Some(end_time) = self.resale_config.resale_end_time {
+            if current_time > end_time {
+                return Err(TicketError::ResaleWindowClosed);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Indica si la reventa terminó: cerró la ventana de reventa o inició el evento
+    fn resale_closed(&self, current_time: u64) -> bool {
+        self.resale_config
+            .resale_end_time
+            .is_some_and(|end_time| current_time > end_time)
+            || current_time >= self.event_start_time
+    }
+    
+    /// Último instante en que puede cerrar una reventa: el fin de la ventana de reventa
+    /// o, si es anterior, justo antes del inicio del evento
+    fn resale_deadline(&self) -> u64 {
+        let before_start = self.event_start_time.saturating_sub(1);
+        match self.resale_config.resale_end_time {
+            Some(end_time) => end_time.min(before_start),
+            None => before_start,
+        }
+    }
+    
+    /// Valida un precio de reventa de `ticket` contra los límites del evento:
+    /// precio máximo, precio mínimo y recargo máximo sobre el precio de compra
+    /// (si no se registró on-chain, el recargo se mide contra el precio de lista de su zona)
+    fn check_resale_price(&self, price: U256, ticket: &Ticket) -> Result<(), TicketError> {
+        if let Some(max_price) = self.resale_config.max_price {
+            if price > max_price {
+                return Err(TicketError::PriceExceedsMaximum);
+            }
+        }
+        if let Some(min_price) = self.resale_config.min_price {
+            if price < min_price {
+                return Err(TicketError::PriceBelowMinimum);
+            }
+        }
+        if let Some(max_markup_bps) = self.resale_config.max_markup_bps {
+            let base = if ticket.purchase_price.is_zero() {
+                self.primary_price_for(&ticket.zone)
+            } else {
+                Some(ticket.purchase_price)
+            };
+            if let Some(base) = base {
+                let cap = base * (U256::from(BASIS_POINTS) + U256::from(max_markup_bps))
+                    / U256::from(BASIS_POINTS);
+                if price > cap {
+                    return Err(TicketError::MarkupExceedsMaximum);
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Busca una zona de la tabla por nombre
+    pub fn zone(&self, name: &str) -> Option<&EventZone> {
+        self.zones.iter().find(|zone| zone.name == name)
+    }
+    
+    /// Precio unitario de venta primaria para una zona (None = no a la venta).
+    /// Sin tabla de zonas, la zona es una etiqueta libre y aplica `primary_price`.
+    fn primary_price_for(&self, zone: &Option<String>) -> Option<U256> {
+        match zone {
+            Some(name) if !self.zones.is_empty() => self.zone(name).and_then(|zone| zone.face_price),
+            _ => self.primary_price,
+        }
+    }
+    
+    /// Valida que haya capacidad para mintear un ticket por cada zona de `zones`.
+    /// Si el evento tiene tabla de zonas, cada ticket debe indicar una zona existente.
+    fn check_zone_capacity(&self, zones: &[Option<String>]) -> Result<(), TicketError> {
+        if self.zones.is_empty() {
+            return Ok(());
+        }
+        for zone in self.zones.iter() {
+            let requested = zones
+                .iter()
+                .filter(|name| name.as_deref() == Some(zone.name.as_str()))
+                .count();
+            if zone.minted + U256::from(requested) > zone.capacity {
+                return Err(TicketError::ZoneSoldOut);
+            }
+        }
+        if zones
+            .iter()
+            .any(|name| name.as_ref().is_none_or(|name| self.zone(name).is_none()))
+        {
+            return Err(TicketError::ZoneNotFound);
+        }
+        Ok(())
+    }
+    
+    /// Asigna un asiento a cada ticket cuya zona tenga mapa de asientos.
+    /// `requested` indica el asiento elegido por ticket (vacío o None = mejor disponible);
+    /// `assigned` son los asientos ya emitidos del contrato.
+    fn allocate_seats(
+        &self,
+        zones: &[Option<String>],
+        requested: &[Option<Seat>],
+        assigned: &HashMap<(U256, Seat), U256>,
+    ) -> Result<Vec<Option<Seat>>, TicketError> {
+        let mut seats: Vec<Option<Seat>> = vec![None; zones.len()];
+        
+        // Asientos elegidos explícitamente
+        for (i, zone) in zones.iter().enumerate() {
+            let Some(seat) = requested.get(i).cloned().flatten() else {
+                continue;
+            };
+            let seat_map = zone.as_ref().and_then(|name| self.zone(name)).map(|zone| &zone.seats);
+            if !seat_map.is_some_and(|seat_map| seat_map.contains(&seat)) {
+                return Err(TicketError::SeatNotFound);
+            }
+            if assigned.contains_key(&(self.event_id, seat.clone())) || seats.contains(&Some(seat.clone())) {
+                return Err(TicketError::SeatTaken);
+            }
+            seats[i] = Some(seat);
+        }
+        
+        // Mejor disponible para el resto, agrupado por zona
+        for zone in self.zones.iter().filter(|zone| !zone.seats.is_empty()) {
+            let pending: Vec<usize> = (0..zones.len())
+                .filter(|i| seats[*i].is_none() && zones[*i].as_deref() == Some(zone.name.as_str()))
+                .collect();
+            if pending.is_empty() {
+                continue;
+            }
+            let free: Vec<&Seat> = zone
+                .seats
+                .iter()
+                .filter(|seat| {
+                    !assigned.contains_key(&(self.event_id, (*seat).clone())) && !seats.contains(&Some((*seat).clone()))
+                })
+                .collect();
+            let picked = best_available(&free, pending.len());
+            if picked.len() < pending.len() {
+                return Err(TicketError::ZoneSoldOut);
+            }
+            for (i, seat) in pending.into_iter().zip(picked) {
+                seats[i] = Some(seat.clone());
+            }
+        }
+        
+        Ok(seats)
+    }
+}
+
+/// Zona de un evento con su capacidad y precio de lista
 #[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
 #[codec(crate = sails_rs::scale_codec)]
 #[scale_info(crate = sails_rs::scale_info)]
-pub struct ResaleConfig {
-    pub enabled: bool, // Si la reventa está habilitada
-    pub max_price: Option<U256>, // Precio máximo permitido (None = sin límite)
-    pub resale_start_time: Option<u64>, // Inicio de ventana de reventa (None = inmediato)
-    pub resale_end_time: Option<u64>, // Fin de ventana de reventa (None = sin límite)
+pub struct EventZone {
+    pub zone_id: u32,
+    pub name: String, // Único dentro del evento; es el valor de `Ticket.zone`
+    pub capacity: U256,
+    pub face_price: Option<U256>, // Precio de venta primaria pública (None = solo minteo del organizador)
+    pub minted: U256,
+    pub seats: Vec<Seat>, // Mapa de asientos en orden de preferencia (vacío = entrada general)
 }
 
-/// Configuración de comisiones
+/// Asiento numerado de un evento
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq, Hash)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct Seat {
+    pub section: String,
+    pub row: String,
+    pub number: u32, // Asientos contiguos de una fila tienen números consecutivos
+}
+
+/// Definición de una zona al crear o actualizar un evento
 #[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
 #[codec(crate = sails_rs::scale_codec)]
 #[scale_info(crate = sails_rs::scale_info)]
-pub struct CommissionConfig {
-    pub seller_percentage: u16, // Porcentaje para el vendedor (en basis points)
-    pub organizer_percentage: u16, // Porcentaje para el organizador
-    pub platform_percentage: u16, // Porcentaje para la plataforma
-    // La suma debe ser 10000 (100%)
+pub struct ZoneConfig {
+    pub name: String,
+    pub capacity: U256,
+    pub face_price: Option<U256>,
 }
 
-/// Información de un listado activo en el Marketplace
+/// Configuración de reventa
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct ResaleConfig {
+    pub enabled: bool, // Si la reventa está habilitada
+    pub max_price: Option<U256>, // Precio máximo permitido (None = sin límite)
+    pub min_price: Option<U256>, // Precio mínimo permitido (None = sin límite)
+    pub max_markup_bps: Option<u32>, // Recargo máximo sobre el precio de compra original, en basis points (None = sin límite)
+    pub resale_start_time: Option<u64>, // Inicio de ventana de reventa (None = inmediato)
+    pub resale_end_time: Option<u64>, // Fin de ventana de reventa (None = sin límite)
+}
+
+/// Cuotas por billetera de un evento (None = sin límite)
+#[derive(Debug, Clone, Default, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct WalletLimits {
+    pub max_primary_purchases: Option<u32>, // Tickets que una billetera puede obtener en venta primaria
+    pub max_holdings: Option<u32>, // Tickets que una billetera puede tener a la vez
+    pub max_resales: Option<u32>, // Reventas que una billetera puede concretar
+}
+
+impl WalletLimits {
+    /// Valida una compra primaria de `amount` tickets
+    fn check_primary(&self, purchased: u32, held: u32, amount: u64) -> Result<(), TicketError> {
+        if let Some(max) = self.max_primary_purchases {
+            if purchased as u64 + amount > max as u64 {
+                return Err(TicketError::PrimaryLimitExceeded);
+            }
+        }
+        self.check_holding(held, amount)
+    }
+    
+    /// Valida que el poseedor pueda recibir `incoming` tickets más
+    fn check_holding(&self, held: u32, incoming: u64) -> Result<(), TicketError> {
+        if let Some(max) = self.max_holdings {
+            if held as u64 + incoming > max as u64 {
+                return Err(TicketError::HoldingLimitExceeded);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Valida que el vendedor pueda concretar una reventa más
+    fn check_resale(&self, resold: u32) -> Result<(), TicketError> {
+        if let Some(max) = self.max_resales {
+            if resold >= max {
+                return Err(TicketError::ResaleLimitExceeded);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Configuración de comisiones
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct CommissionConfig {
+    pub seller_percentage: u16, // Porcentaje para el vendedor (en basis points)
+    pub organizer_percentage: u16, // Porcentaje para el organizador
+    pub platform_percentage: u16, // Porcentaje para la plataforma
+    pub beneficiaries: Vec<(ActorId, u16)>, // Regalías adicionales (artista, venue, beneficencia) en basis points
+    // La suma de todos los porcentajes debe ser 10000 (100%)
+}
+
+/// Reparto de un precio de venta según `CommissionConfig`
+/// Siempre se cumple: seller_share + organizer_share + platform_share
+/// + suma de beneficiary_shares == precio
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaleSplit {
+    pub seller_share: U256,
+    pub organizer_share: U256,
+    pub platform_share: U256,
+    pub beneficiary_shares: Vec<(ActorId, U256)>,
+    pub rounding_remainder: U256, // Resto del redondeo asignado al vendedor
+}
+
+impl CommissionConfig {
+    /// Reparte `price` entre vendedor, organizador, plataforma y beneficiarios.
+    /// Todas las participaciones salvo la del vendedor se redondean hacia abajo
+    /// y el resto del redondeo se asigna al vendedor.
+    pub fn split(&self, price: U256) -> SaleSplit {
+        let share = |bps: u16| price * U256::from(bps) / U256::from(BASIS_POINTS);
+        let organizer_share = share(self.organizer_percentage);
+        let platform_share = share(self.platform_percentage);
+        let beneficiary_shares: Vec<(ActorId, U256)> = self
+            .beneficiaries
+            .iter()
+            .map(|(beneficiary, bps)| (*beneficiary, share(*bps)))
+            .collect();
+        let beneficiaries_total = beneficiary_shares
+            .iter()
+            .fold(U256::zero(), |total, (_, amount)| total + *amount);
+        let seller_share = price - organizer_share - platform_share - beneficiaries_total;
+        SaleSplit {
+            seller_share,
+            organizer_share,
+            platform_share,
+            beneficiary_shares,
+            rounding_remainder: seller_share - share(self.seller_percentage),
+        }
+    }
+}
+
+/// Información de un listado activo en el Marketplace
 #[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
 #[codec(crate = sails_rs::scale_codec)]
 #[scale_info(crate = sails_rs::scale_info)]
 pub struct Listing {
     pub ticket_id: U256,
     pub seller: ActorId,
-    pub price: U256,
+    pub price: U256, // Precio fijo, o precio inicial en subasta holandesa
     pub listed_at: u64,
     pub event_id: U256, // Para validaciones rápidas
+    pub kind: ListingKind,
+    pub expires_at: Option<u64>, // Vencimiento del listado (None = sin vencimiento propio)
+    pub reserved_for: Option<ActorId>, // Único comprador permitido (None = listado público)
+}
+
+/// Modalidad de un listado
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub enum ListingKind {
+    /// Precio fijo
+    FixedPrice,
+    /// Subasta holandesa: el precio baja linealmente desde `price` hasta
+    /// `floor_price` entre `listed_at` y `ends_at`, y luego queda en `floor_price`
+    Dutch { floor_price: U256, ends_at: u64 },
+}
+
+/// Motivo por el que se retiró un listado
+#[derive(Debug, Clone, Copy, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub enum ListingCancelReason {
+    /// El vendedor canceló el listado
+    SellerCancelled,
+    /// El ticket fue marcado como usado
+    TicketUsed,
+    /// El ticket cambió de dueño fuera del listado
+    TicketTransferred,
+    /// El evento fue desactivado
+    EventDisabled,
+    /// El evento fue cancelado
+    EventCancelled,
+    /// El paquete venció
+    Expired,
+}
+
+impl Listing {
+    /// Indica si el listado es visible en las consultas públicas
+    pub fn is_public(&self) -> bool {
+        self.reserved_for.is_none()
+    }
+    
+    /// Indica si el listado venció: por su propio vencimiento, por el cierre
+    /// de la ventana de reventa o por el inicio del evento
+    pub fn is_expired(&self, event_config: &EventConfig, current_time: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| current_time > expires_at)
+            || event_config.resale_closed(current_time)
+    }
+    
+    /// Precio de compra vigente en `current_time`
+    pub fn current_price(&self, current_time: u64) -> U256 {
+        match self.kind {
+            ListingKind::FixedPrice => self.price,
+            ListingKind::Dutch {
+                floor_price,
+                ends_at,
+            } => {
+                if current_time >= ends_at {
+                    return floor_price;
+                }
+                let elapsed = current_time.saturating_sub(self.listed_at);
+                let duration = ends_at - self.listed_at;
+                let drop = (self.price - floor_price) * U256::from(elapsed) / U256::from(duration);
+                self.price - drop
+            }
+        }
+    }
+}
+
+/// Subasta inglesa de un ticket, con la puja más alta en escrow
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct Auction {
+    pub ticket_id: U256,
+    pub event_id: U256,
+    pub seller: ActorId,
+    pub reserve_price: U256, // Primera puja mínima
+    pub min_increment: U256, // Incremento mínimo sobre la puja más alta
+    pub started_at: u64,
+    pub end_time: u64, // Se extiende si llega una puja cerca del cierre
+    pub highest_bidder: Option<ActorId>,
+    pub highest_bid: U256,
+    pub currency: Option<ActorId>, // Moneda de las pujas en escrow
+}
+
+/// Paquete de tickets de un mismo evento vendidos juntos a un único precio
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct Bundle {
+    pub bundle_id: U256,
+    pub event_id: U256,
+    pub seller: ActorId,
+    pub ticket_ids: Vec<U256>,
+    pub price: U256, // Precio del paquete completo
+    pub listed_at: u64,
+}
+
+impl Bundle {
+    /// Indica si el paquete venció: por el cierre de la ventana de reventa o por el inicio del evento
+    pub fn is_expired(&self, event_config: &EventConfig, current_time: u64) -> bool {
+        event_config.resale_closed(current_time)
+    }
+}
+
+/// Oferta sobre un ticket (listado o no), con el precio en escrow
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct Offer {
+    pub offer_id: U256,
+    pub ticket_id: U256,
+    pub event_id: U256,
+    pub bidder: ActorId,
+    pub price: U256,
+    pub currency: Option<ActorId>, // Moneda en que se depositó el escrow
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+/// Lugar en la lista de espera del fan exchange, con el depósito en escrow
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct WaitlistEntry {
+    pub buyer: ActorId,
+    pub deposit: U256, // Precio de lista al momento de sumarse (moneda del evento)
+    pub joined_at: u64,
 }
 
 /// Estructura de un ticket NFT
@@ -99,11 +1069,13 @@ pub struct Listing {
 pub struct Ticket {
     pub ticket_id: U256,
     pub event_id: U256,
-    pub zone: Option<String>, // Zona/asiento opcional
+    pub zone: Option<String>, // Zona opcional
+    pub seat: Option<Seat>, // Asiento numerado (solo en zonas con mapa de asientos)
     pub original_buyer: ActorId, // Comprador original
     pub current_owner: ActorId, // Propietario actual
     pub used: bool, // Si el ticket ha sido usado
     pub minted_at: u64, // Timestamp de creación
+    pub purchase_price: U256, // Precio pagado on-chain en venta primaria (0 si se vendió fuera del contrato)
 }
 
 /// Eventos emitidos por el contrato
@@ -126,6 +1098,7 @@ pub enum Event {
         buyer: ActorId,
         amount: U256,
         zones: Vec<Option<String>>,
+        seats: Vec<Option<Seat>>,
     },
     /// Ticket revendido
     TicketResold {
@@ -137,6 +1110,8 @@ pub enum Event {
         seller_share: U256,
         organizer_share: U256,
         platform_share: U256,
+        beneficiary_shares: Vec<(ActorId, U256)>, // Regalías pagadas a beneficiarios
+        rounding_remainder: U256, // Resto del redondeo, incluido en seller_share
     },
     /// Ticket marcado como usado
     TicketUsed {
@@ -170,6 +1145,16 @@ pub enum Event {
         event_id: U256,
         seller: ActorId,
         price: U256,
+        reserved_for: Option<ActorId>, // Listado privado: los indexadores no deben publicarlo
+    },
+    /// Ticket listado en subasta holandesa
+    TicketListedDutch {
+        ticket_id: U256,
+        event_id: U256,
+        seller: ActorId,
+        start_price: U256,
+        floor_price: U256,
+        ends_at: u64,
     },
     /// Ticket vendido desde el Marketplace
     TicketSold {
@@ -181,17 +1166,176 @@ pub enum Event {
         seller_share: U256,
         organizer_share: U256,
         platform_share: U256,
+        beneficiary_shares: Vec<(ActorId, U256)>, // Regalías pagadas a beneficiarios
+        rounding_remainder: U256, // Resto del redondeo, incluido en seller_share
     },
     /// Listado cancelado
     ListingCancelled {
         ticket_id: U256,
         event_id: U256,
         seller: ActorId,
+        reason: ListingCancelReason,
+    },
+    /// Evento cancelado (terminal)
+    EventCancelled {
+        event_id: U256,
+    },
+    /// Ticket reembolsado y quemado tras la cancelación del evento
+    TicketRefunded {
+        ticket_id: U256,
+        event_id: U256,
+        holder: ActorId,
+        amount: U256,
+    },
+    /// Recaudación primaria liberada del escrow al organizador
+    PrimaryRevenueReleased {
+        event_id: U256,
+        organizer: ActorId,
+        amount: U256,
+    },
+    /// Destinatario de comisiones de plataforma actualizado
+    PlatformFeeRecipientUpdated {
+        recipient: ActorId,
+    },
+    /// Comisiones de plataforma retiradas
+    PlatformFeesWithdrawn {
+        recipient: ActorId,
+        currency: Option<ActorId>,
+        amount: U256,
+    },
+    /// Subasta creada
+    AuctionCreated {
+        ticket_id: U256,
+        event_id: U256,
+        seller: ActorId,
+        reserve_price: U256,
+        min_increment: U256,
+        end_time: u64,
+    },
+    /// Puja registrada (end_time refleja una posible extensión anti-sniping)
+    BidPlaced {
+        ticket_id: U256,
+        bidder: ActorId,
+        amount: U256,
+        end_time: u64,
+    },
+    /// Subasta liquidada con venta al mejor postor
+    AuctionSettled {
+        ticket_id: U256,
+        event_id: U256,
+        seller: ActorId,
+        winner: ActorId,
+        price: U256,
+        seller_share: U256,
+        organizer_share: U256,
+        platform_share: U256,
+        beneficiary_shares: Vec<(ActorId, U256)>, // Regalías pagadas a beneficiarios
+        rounding_remainder: U256,
+    },
+    /// Subasta cancelada o finalizada sin venta
+    AuctionCancelled {
+        ticket_id: U256,
+        event_id: U256,
+        seller: ActorId,
+    },
+    /// Oferta realizada sobre un ticket
+    OfferMade {
+        offer_id: U256,
+        ticket_id: U256,
+        event_id: U256,
+        bidder: ActorId,
+        price: U256,
+        expires_at: u64,
+    },
+    /// Oferta aceptada por el propietario del ticket
+    OfferAccepted {
+        offer_id: U256,
+        ticket_id: U256,
+        event_id: U256,
+        seller: ActorId,
+        buyer: ActorId,
+        price: U256,
+        seller_share: U256,
+        organizer_share: U256,
+        platform_share: U256,
+        beneficiary_shares: Vec<(ActorId, U256)>, // Regalías pagadas a beneficiarios
+        rounding_remainder: U256,
+    },
+    /// Oferta cancelada y escrow devuelto
+    OfferCancelled {
+        offer_id: U256,
+        ticket_id: U256,
+        event_id: U256,
+        bidder: ActorId,
+    },
+    /// Comprador sumado a la lista de espera del fan exchange
+    WaitlistJoined {
+        event_id: U256,
+        zone: Option<String>,
+        buyer: ActorId,
+        deposit: U256,
+    },
+    /// Comprador retirado de la lista de espera (depósito devuelto)
+    WaitlistLeft {
+        event_id: U256,
+        zone: Option<String>,
+        buyer: ActorId,
+    },
+    /// Precio de un listado actualizado
+    ListingPriceUpdated {
+        ticket_id: U256,
+        event_id: U256,
+        seller: ActorId,
+        old_price: U256,
+        new_price: U256,
+    },
+    /// Paquete de tickets listado
+    BundleListed {
+        bundle_id: U256,
+        event_id: U256,
+        seller: ActorId,
+        ticket_ids: Vec<U256>,
+        price: U256,
+    },
+    /// Paquete vendido completo (cada ticket emite además `TicketSold`)
+    BundleSold {
+        bundle_id: U256,
+        event_id: U256,
+        seller: ActorId,
+        buyer: ActorId,
+        price: U256,
+    },
+    /// Paquete retirado
+    BundleCancelled {
+        bundle_id: U256,
+        event_id: U256,
+        seller: ActorId,
+        reason: ListingCancelReason,
+    },
+    /// Listado vencido removido
+    ListingExpired {
+        ticket_id: U256,
+        event_id: U256,
+        seller: ActorId,
+    },
+    /// Saldo reclamable retirado
+    Withdrawn {
+        account: ActorId,
+        currency: Option<ActorId>,
+        amount: U256,
+    },
+    /// Una operación falló después de esperar a otro contrato; el estado se restauró
+    /// y los fondos cobrados se devolvieron
+    OperationFailed {
+        account: ActorId,
+        error: TicketError,
     },
 }
 
 /// Errores del contrato
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
 pub enum TicketError {
     Unauthorized,
     EventNotFound,
@@ -213,6 +1357,43 @@ pub enum TicketError {
     ListingNotFound,
     InvalidPrice,
     PurchaseFailed,
+    InsufficientPayment,
+    PaymentFailed,
+    PrimarySaleDisabled,
+    NothingToWithdraw,
+    EventCancelled,
+    EventNotCancelled,
+    EventNotStarted,
+    InsufficientEscrow,
+    OfferNotFound,
+    OfferExpired,
+    AuctionNotFound,
+    AuctionEnded,
+    AuctionNotEnded,
+    AuctionHasBids,
+    BidTooLow,
+    ListingExpired,
+    PriceBelowMinimum,
+    MarkupExceedsMaximum,
+    PrimaryLimitExceeded,
+    HoldingLimitExceeded,
+    ResaleLimitExceeded,
+    FanExchangeDisabled,
+    AlreadyInWaitlist,
+    NotInWaitlist,
+    ListingReserved,
+    BundleNotFound,
+    ZoneNotFound,
+    ZoneSoldOut,
+    SeatNotFound,
+    SeatTaken,
+    MintFailed,
+    TransferFailed,
+    BurnFailed,
+    NothingToRefund,
+    RevenueAlreadyReleased,
+    OperationPending,
+    PrimarySaleClosed,
 }
 
 static mut STORAGE: Option<Storage> = None;
@@ -252,9 +1433,11 @@ impl TicketService {
         storage.locked = true;
     }
     
+    /// Libera el lock y los tickets reservados por la operación en curso
     fn unlock(&mut self) {
         let storage = self.get_mut();
         storage.locked = false;
+        storage.pending_tickets.clear();
     }
     
     /// Valida que el caller sea admin
@@ -281,263 +1464,2357 @@ impl TicketService {
         if caller != storage.admin && !storage.scanners.contains(&caller) {
             panic(TicketError::Unauthorized);
         }
-    }
-    
-    /// Valida configuración de comisiones
-    fn validate_commission_config(config: &CommissionConfig) {
-        let total = config.seller_percentage 
-            + config.organizer_percentage 
-            + config.platform_percentage;
-        if total != BASIS_POINTS {
-            panic(TicketError::InvalidCommissionConfig);
+    }
+    
+    /// Valida configuración de comisiones
+    fn validate_commission_config(config: &CommissionConfig) {
+        if config.beneficiaries.len() > MAX_BENEFICIARIES {
+            panic(TicketError::InvalidCommissionConfig);
+        }
+        
+        // Beneficiarios válidos, con porcentaje y sin repetidos
+        for (i, (beneficiary, bps)) in config.beneficiaries.iter().enumerate() {
+            if *beneficiary == ZERO_ID || *bps == 0 {
+                panic(TicketError::InvalidCommissionConfig);
+            }
+            if config.beneficiaries[..i].iter().any(|(other, _)| other == beneficiary) {
+                panic(TicketError::InvalidCommissionConfig);
+            }
+        }
+        
+        let total = config.seller_percentage as u32
+            + config.organizer_percentage as u32
+            + config.platform_percentage as u32
+            + config.beneficiaries.iter().map(|(_, bps)| *bps as u32).sum::<u32>();
+        if total != BASIS_POINTS as u32 {
+            panic(TicketError::InvalidCommissionConfig);
+        }
+    }
+    
+    /// Valida configuración de reventa
+    fn validate_resale_config(config: &ResaleConfig) {
+        if let (Some(start), Some(end)) = (config.resale_start_time, config.resale_end_time) {
+            if start >= end {
+                panic(TicketError::InvalidResaleConfig);
+            }
+        }
+        if let (Some(min), Some(max)) = (config.min_price, config.max_price) {
+            if min > max {
+                panic(TicketError::InvalidResaleConfig);
+            }
+        }
+    }
+    
+    /// Valida el precio de venta primaria general
+    fn validate_primary_price(primary_price: &Option<U256>) {
+        if *primary_price == Some(U256::zero()) {
+            panic(TicketError::InvalidPrice);
+        }
+    }
+    
+    /// Valida la tabla de zonas contra la capacidad total del evento
+    fn validate_zones(zones: &[ZoneConfig], tickets_total: U256) {
+        let mut capacity_total = U256::zero();
+        for (i, zone) in zones.iter().enumerate() {
+            if zone.name.is_empty() || zone.capacity.is_zero() {
+                panic(TicketError::InvalidInput);
+            }
+            if zone.face_price == Some(U256::zero()) {
+                panic(TicketError::InvalidPrice);
+            }
+            if zones[..i].iter().any(|other| other.name == zone.name) {
+                panic(TicketError::InvalidInput);
+            }
+            capacity_total += zone.capacity;
+        }
+        if capacity_total > tickets_total {
+            panic(TicketError::InvalidAmount);
+        }
+    }
+    
+    /// Obtiene timestamp actual
+fn current_timestamp(&self) -> u64 {
+    exec::block_timestamp()
+}
+}
+
+#[service(events = Event)]
+impl TicketService {
+    // Auxiliares que emiten eventos: sails genera `emit_event` solo para los
+    // métodos del bloque `#[service]`
+    /// Termina una operación que falló después de un `await`: el caller ya restauró
+    /// el estado y devolvió los fondos. Emite `OperationFailed` y libera el lock sin
+    /// entrar en pánico (un pánico en este punto no revierte lo hecho antes del `await`)
+    fn abort(&mut self, account: ActorId, error: TicketError) {
+        let _ = self.emit_event(Event::OperationFailed { account, error });
+        self.unlock();
+    }
+    
+    /// Emite los eventos devueltos por una operación de `Storage`
+    fn emit_events(&mut self, events: Vec<Event>) {
+        for event in events {
+            self.emit_event(event).expect("Failed to emit event");
+        }
+    }
+    
+    /// Crear un nuevo evento
+    /// Solo admin u organizador autorizado
+    #[export]
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_event(
+        &mut self,
+        organizer: ActorId,
+        metadata_hash: [u8; 32],
+        event_start_time: u64,
+        tickets_total: U256,
+        resale_config: ResaleConfig,
+        commission_config: CommissionConfig,
+        payment_token: Option<ActorId>,
+        primary_price: Option<U256>,
+        zones: Vec<ZoneConfig>,
+        wallet_limits: WalletLimits,
+    ) {
+        self.require_organizer();
+        
+        if organizer == ZERO_ID || payment_token == Some(ZERO_ID) {
+            panic(TicketError::InvalidInput);
+        }
+        
+        if tickets_total == U256::zero() {
+            panic(TicketError::InvalidAmount);
+        }
+        
+        TicketService::validate_resale_config(&resale_config);
+        TicketService::validate_commission_config(&commission_config);
+        TicketService::validate_primary_price(&primary_price);
+        TicketService::validate_zones(&zones, tickets_total);
+        
+        let storage = self.get_mut();
+        storage.event_id_counter += U256::one();
+        let event_id = storage.event_id_counter;
+        
+        let event_config = EventConfig {
+            event_id,
+            organizer,
+            metadata_hash,
+            event_start_time,
+            tickets_minted: U256::zero(),
+            tickets_total,
+            resale_config,
+            commission_config,
+            active: true,
+            payment_token,
+            primary_price,
+            zones: zones
+                .into_iter()
+                .enumerate()
+                .map(|(i, zone)| EventZone {
+                    zone_id: i as u32 + 1,
+                    name: zone.name,
+                    capacity: zone.capacity,
+                    face_price: zone.face_price,
+                    minted: U256::zero(),
+                    seats: Vec::new(),
+                })
+                .collect(),
+            cancelled: false,
+            wallet_limits,
+            fan_exchange: false,
+        };
+        
+        storage.events.insert(event_id, event_config.clone());
+        storage.event_tickets.insert(event_id, Vec::new());
+        
+        // Si el organizador no está en la lista, agregarlo
+        storage.organizers.insert(organizer);
+        
+        self.emit_event(Event::EventCreated {
+            event_id,
+            organizer,
+            metadata_hash,
+            event_start_time,
+        })
+        .expect("Failed to emit EventCreated");
+    }
+    
+    /// Mintear tickets en venta primaria (batch minting)
+    /// Solo organizador del evento o admin
+    #[export]
+    pub async fn mint_tickets(
+        &mut self,
+        event_id: U256,
+        buyer: ActorId,
+        amount: U256,
+        zones: Vec<Option<String>>, // Zonas opcionales para cada ticket
+        seats: Vec<Option<Seat>>, // Asientos elegidos por ticket (vacío o None = mejor disponible)
+    ) {
+        self.non_reentrant();
+        
+        if buyer == ZERO_ID {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        
+        if amount == U256::zero() {
+            self.unlock();
+            panic(TicketError::InvalidAmount);
+        }
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            self.unlock();
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            self.unlock();
+            panic(TicketError::Unauthorized);
+        }
+        
+        if !event_config.active {
+            self.unlock();
+            panic(TicketError::EventNotActive);
+        }
+        
+        // Validar disponibilidad
+        let available = event_config.tickets_total - event_config.tickets_minted;
+        if amount > available {
+            self.unlock();
+            panic(TicketError::NotEnoughTickets);
+        }
+        
+        // Validar que las zonas coincidan con la cantidad
+        if !zones.is_empty() && U256::from(zones.len()) != amount {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        
+        // Validar capacidad por zona
+        let requested_zones: Vec<Option<String>> = if zones.is_empty() {
+            vec![None; amount.as_usize()]
+        } else {
+            zones
+        };
+        if let Err(e) = event_config.check_zone_capacity(&requested_zones) {
+            self.unlock();
+            panic(e);
+        }
+        
+        // Asignar asientos numerados
+        if !seats.is_empty() && U256::from(seats.len()) != amount {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        let seats = match event_config.allocate_seats(&requested_zones, &seats, &storage.seat_assignments) {
+            Ok(seats) => seats,
+            Err(e) => {
+                self.unlock();
+                panic(e);
+            }
+        };
+        
+        // Validar cuotas por billetera
+        let purchased = storage.primary_purchases.get(&(event_id, buyer)).copied().unwrap_or_default();
+        let held = storage.holdings.get(&(event_id, buyer)).copied().unwrap_or_default();
+        if let Err(e) = event_config.wallet_limits.check_primary(purchased, held, amount.as_u64()) {
+            self.unlock();
+            panic(e);
+        }
+        
+        // Actualizar contador de tickets minteados
+        event_config.tickets_minted += amount;
+        
+        // Crear tickets (venta fuera del contrato: no hay precio pagado on-chain)
+        let current_time = self.current_timestamp();
+        let (ticket_ids, zones_emitted, seats_emitted) =
+            storage.create_tickets(event_id, buyer, requested_zones.into_iter().zip(seats).collect(), U256::zero(), current_time);
+        storage.pending_tickets.extend(ticket_ids.iter().copied());
+        
+        // Mintear NFTs usando VMT; si falla, deshacer la creación de los tickets
+        if !mint_ticket_nfts(storage.vmt_contract_id, buyer, &ticket_ids).await {
+            storage.discard_tickets(event_id, buyer, &ticket_ids);
+            if let Some(event_config) = storage.events.get_mut(&event_id) {
+                event_config.tickets_minted -= amount;
+            }
+            self.abort(caller, TicketError::MintFailed);
+            return;
+        }
+        
+        self.emit_event(Event::TicketsMinted {
+            event_id,
+            ticket_ids: ticket_ids.clone(),
+            buyer,
+            amount,
+            zones: zones_emitted,
+            seats: seats_emitted,
+        })
+        .expect("Failed to emit TicketsMinted");
+        
+        self.unlock();
+    }
+    
+    /// Comprar tickets en venta primaria pública
+    /// Pagable: cobra el precio de lista del evento (o de la zona) y mintea los tickets al caller
+    #[export]
+    pub async fn buy_primary(
+        &mut self,
+        event_id: U256,
+        zone: Option<String>,
+        amount: U256,
+        seats: Vec<Seat>, // Asientos elegidos (vacío = mejor disponible)
+    ) {
+        self.non_reentrant();
+        
+        let buyer = msg::source();
+        
+        if amount == U256::zero() {
+            self.unlock();
+            panic(TicketError::InvalidAmount);
+        }
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            self.unlock();
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        if !event_config.active {
+            self.unlock();
+            panic(TicketError::EventNotActive);
+        }
+        
+        // La venta primaria cierra al iniciar el evento o al liberarse su recaudación
+        let current_time = self.current_timestamp();
+        if current_time >= event_config.event_start_time || storage.primary_released.contains(&event_id) {
+            self.unlock();
+            panic(TicketError::PrimarySaleClosed);
+        }
+        
+        // Obtener precio de lista
+        let unit_price = event_config.primary_price_for(&zone);
+        if unit_price.is_none() {
+            self.unlock();
+            panic(TicketError::PrimarySaleDisabled);
+        }
+        let unit_price = unit_price.unwrap();
+        
+        // Validar disponibilidad
+        let available = event_config.tickets_total - event_config.tickets_minted;
+        if amount > available {
+            self.unlock();
+            panic(TicketError::NotEnoughTickets);
+        }
+        
+        // Validar capacidad de la zona
+        let zones: Vec<Option<String>> = core::iter::repeat_n(zone, amount.as_usize()).collect();
+        if let Err(e) = event_config.check_zone_capacity(&zones) {
+            self.unlock();
+            panic(e);
+        }
+        
+        // Asignar asientos numerados
+        if !seats.is_empty() && U256::from(seats.len()) != amount {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        let requested: Vec<Option<Seat>> = seats.into_iter().map(Some).collect();
+        let seats = match event_config.allocate_seats(&zones, &requested, &storage.seat_assignments) {
+            Ok(seats) => seats,
+            Err(e) => {
+                self.unlock();
+                panic(e);
+            }
+        };
+        
+        // Validar cuotas por billetera
+        let purchased = storage.primary_purchases.get(&(event_id, buyer)).copied().unwrap_or_default();
+        let held = storage.holdings.get(&(event_id, buyer)).copied().unwrap_or_default();
+        if let Err(e) = event_config.wallet_limits.check_primary(purchased, held, amount.as_u64()) {
+            self.unlock();
+            panic(e);
+        }
+        
+        let total_price = unit_price.checked_mul(amount);
+        if total_price.is_none() {
+            self.unlock();
+            panic(TicketError::InvalidAmount);
+        }
+        let total_price = total_price.unwrap();
+        
+        // Validar el pago recibido (VARA nativo si el evento no liquida en token)
+        let currency = event_config.payment_token;
+        let payment = msg::value();
+        if currency.is_none() && U256::from(payment) < total_price {
+            self.unlock();
+            panic(TicketError::InsufficientPayment);
+        }
+        
+        // Reservar los tickets antes de cobrar: la disponibilidad, la zona y los asientos
+        // quedan tomados mientras se espera el pago y el minteo, y se liberan si alguno falla
+        event_config.tickets_minted += amount;
+        let (ticket_ids, zones_emitted, seats_emitted) =
+            storage.create_tickets(event_id, buyer, zones.into_iter().zip(seats).collect(), unit_price, current_time);
+        storage.pending_tickets.extend(ticket_ids.iter().copied());
+        
+        // Si el evento liquida en token, cobrar el precio al comprador
+        let mut failure = None;
+        if let Some(token) = currency {
+            if !vft_transfer_from(token, buyer, exec::program_id(), total_price).await {
+                failure = Some(TicketError::PaymentFailed);
+            }
+        }
+        
+        // Mintear NFTs usando VMT
+        if failure.is_none() && !mint_ticket_nfts(storage.vmt_contract_id, buyer, &ticket_ids).await {
+            failure = Some(TicketError::MintFailed);
+        }
+        
+        // Si el cobro o el minteo fallaron, deshacer la reserva y devolver lo pagado
+        if let Some(error) = failure {
+            storage.discard_tickets(event_id, buyer, &ticket_ids);
+            if let Some(event_config) = storage.events.get_mut(&event_id) {
+                event_config.tickets_minted -= amount;
+            }
+            let collected = if error == TicketError::MintFailed { total_price } else { U256::zero() };
+            storage.return_payment(buyer, currency, collected, payment).await;
+            self.abort(buyer, error);
+            return;
+        }
+        
+        // Retener la recaudación en escrow hasta el inicio del evento. Si mientras se esperaba
+        // el organizador ya liberó la recaudación, esta compra se le acredita directamente.
+        if storage.primary_released.contains(&event_id) {
+            let organizer = storage.events.get(&event_id).expect("Event not found").organizer;
+            storage.credit(organizer, currency, total_price);
+        } else {
+            *storage.primary_escrow.entry(event_id).or_default() += total_price;
+        }
+        storage.financials_mut(event_id).primary_revenue += total_price;
+        
+        // Devolver el excedente al comprador (todo el VARA adjunto si se pagó en token)
+        let refund = if currency.is_none() {
+            payment - total_price.as_u128()
+        } else {
+            payment
+        };
+        storage.refund_value(buyer, refund).await;
+        
+        self.emit_event(Event::TicketsMinted {
+            event_id,
+            ticket_ids,
+            buyer,
+            amount,
+            zones: zones_emitted,
+            seats: seats_emitted,
+        })
+        .expect("Failed to emit TicketsMinted");
+        
+        self.unlock();
+    }
+    
+    /// Revender un ticket
+    /// Solo el propietario actual puede revender
+    #[export]
+    pub async fn resell_ticket(
+        &mut self,
+        ticket_id: U256,
+        buyer: ActorId,
+        price: U256,
+    ) {
+        self.non_reentrant();
+        
+        if buyer == ZERO_ID {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        
+        if price == U256::zero() {
+            self.unlock();
+            panic(TicketError::InvalidAmount);
+        }
+        
+        let current_time = self.current_timestamp();
+        let storage = self.get_mut();
+        
+        let ticket = storage.tickets.get(&ticket_id);
+        if ticket.is_none() {
+            self.unlock();
+            panic(TicketError::TicketNotFound);
+        }
+        let ticket = ticket.unwrap();
+        
+        // Validar que el ticket no esté usado
+        if ticket.used {
+            self.unlock();
+            panic(TicketError::TicketAlreadyUsed);
+        }
+        
+        // Validar propiedad
+        if ticket.current_owner != msg::source() {
+            self.unlock();
+            panic(TicketError::TicketNotOwned);
+        }
+        
+        // Validar que no se transfiera a sí mismo
+        if buyer == ticket.current_owner {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        
+        let event_config = storage.events.get(&ticket.event_id);
+        if event_config.is_none() {
+            self.unlock();
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar evento (cancelado o inactivo), reventa habilitada, límites de precio y ventana
+        if let Err(e) = event_config.check_resale(price, ticket, current_time) {
+            self.unlock();
+            panic(e);
+        }
+        
+        // Validar cuotas por billetera de comprador y vendedor
+        let held = storage.holdings.get(&(ticket.event_id, buyer)).copied().unwrap_or_default();
+        let resold = storage.resales.get(&(ticket.event_id, ticket.current_owner)).copied().unwrap_or_default();
+        if let Err(e) = event_config
+            .wallet_limits
+            .check_holding(held, 1)
+            .and_then(|_| event_config.wallet_limits.check_resale(resold))
+        {
+            self.unlock();
+            panic(e);
+        }
+        
+        let currency = event_config.payment_token;
+        let seller = ticket.current_owner;
+        let event_id = ticket.event_id;
+        
+        // Si el evento liquida en token, el pago sale de una oferta en escrow del comprador
+        // por este ticket y precio: nunca se cobra a una billetera que no firmó el mensaje
+        let mut offer = None;
+        if currency.is_some() {
+            let offer_id = storage.find_offer(ticket_id, buyer, price, currency, current_time);
+            if offer_id.is_none() {
+                self.unlock();
+                panic(TicketError::OfferNotFound);
+            }
+            offer = storage.remove_offer(&offer_id.unwrap());
+        }
+        
+        // Transferir el NFT del vendedor al comprador usando VMT
+        // (si falla, la oferta vuelve a quedar vigente con su escrow)
+        storage.pending_tickets.insert(ticket_id);
+        if !transfer_ticket_nft(storage.vmt_contract_id, seller, buyer, ticket_id).await {
+            if let Some(offer) = offer {
+                storage.insert_offer(offer);
+            }
+            self.abort(seller, TicketError::TransferFailed);
+            return;
+        }
+        
+        // En token se liquida el pago de la oferta. En VARA la reventa directa no cobra nada:
+        // el ticket cambia de manos sin repartos ni volumen de reventa, pero cuenta en la cuota
+        // del vendedor (las ventas pagadas en VARA se hacen a través del Marketplace)
+        let split = if currency.is_some() {
+            storage.settle_resale(ticket_id, buyer, price)
+        } else {
+            if let Some(ticket) = storage.tickets.get_mut(&ticket_id) {
+                ticket.current_owner = buyer;
+            }
+            *storage.resales.entry((event_id, seller)).or_default() += 1;
+            storage.move_holding(event_id, Some(seller), Some(buyer));
+            SaleSplit::default()
+        };
+        
+        // El listado del vendedor anterior ya no aplica
+        let events = storage.delist_ticket(ticket_id, ListingCancelReason::TicketTransferred);
+        self.emit_events(events);
+        
+        self.emit_event(Event::TicketResold {
+            ticket_id,
+            event_id,
+            seller,
+            buyer,
+            price,
+            seller_share: split.seller_share,
+            organizer_share: split.organizer_share,
+            platform_share: split.platform_share,
+            beneficiary_shares: split.beneficiary_shares,
+            rounding_remainder: split.rounding_remainder,
+        })
+        .expect("Failed to emit TicketResold");
+        
+        self.unlock();
+    }
+    
+    /// Marcar un ticket como usado
+    /// Solo escáner autorizado o admin
+    #[export]
+    pub fn mark_ticket_used(&mut self, ticket_id: U256) {
+        self.require_scanner();
+        
+        let storage = self.get_mut();
+        
+        // Un ticket cuya venta o transferencia espera respuesta se marca cuando esta termina
+        if let Err(e) = storage.check_not_pending(&ticket_id) {
+            panic(e);
+        }
+        
+        let ticket = storage.tickets.get_mut(&ticket_id);
+        if ticket.is_none() {
+            panic(TicketError::TicketNotFound);
+        }
+        let ticket = ticket.unwrap();
+        
+        if ticket.used {
+            panic(TicketError::TicketAlreadyUsed);
+        }
+        
+        ticket.used = true;
+        let event_id = ticket.event_id;
+        
+        // Un ticket usado ya no se puede vender
+        let events = storage.delist_ticket(ticket_id, ListingCancelReason::TicketUsed);
+        self.emit_events(events);
+        
+        self.emit_event(Event::TicketUsed {
+            ticket_id,
+            event_id,
+            scanner: msg::source(),
+        })
+        .expect("Failed to emit TicketUsed");
+    }
+    
+    /// Agregar organizador
+    /// Solo admin
+    #[export]
+    pub fn add_organizer(&mut self, organizer: ActorId) {
+        self.require_admin();
+        
+        if organizer == ZERO_ID {
+            panic(TicketError::InvalidInput);
+        }
+        
+        let storage = self.get_mut();
+        storage.organizers.insert(organizer);
+        
+        self.emit_event(Event::OrganizerAdded { organizer })
+            .expect("Failed to emit OrganizerAdded");
+    }
+    
+    /// Remover organizador
+    /// Solo admin
+    #[export]
+    pub fn remove_organizer(&mut self, organizer: ActorId) {
+        self.require_admin();
+        
+        let storage = self.get_mut();
+        storage.organizers.remove(&organizer);
+        
+        self.emit_event(Event::OrganizerRemoved { organizer })
+            .expect("Failed to emit OrganizerRemoved");
+    }
+    
+    /// Agregar escáner
+    /// Solo admin
+    #[export]
+    pub fn add_scanner(&mut self, scanner: ActorId) {
+        self.require_admin();
+        
+        if scanner == ZERO_ID {
+            panic(TicketError::InvalidInput);
+        }
+        
+        let storage = self.get_mut();
+        storage.scanners.insert(scanner);
+        
+        self.emit_event(Event::ScannerAdded { scanner })
+            .expect("Failed to emit ScannerAdded");
+    }
+    
+    /// Remover escáner
+    /// Solo admin
+    #[export]
+    pub fn remove_scanner(&mut self, scanner: ActorId) {
+        self.require_admin();
+        
+        let storage = self.get_mut();
+        storage.scanners.remove(&scanner);
+        
+        self.emit_event(Event::ScannerRemoved { scanner })
+            .expect("Failed to emit ScannerRemoved");
+    }
+    
+    /// Configurar destinatario de comisiones de plataforma
+    /// Solo admin
+    #[export]
+    pub fn set_platform_fee_recipient(&mut self, recipient: ActorId) {
+        self.require_admin();
+        
+        if recipient == ZERO_ID {
+            panic(TicketError::InvalidInput);
+        }
+        
+        let storage = self.get_mut();
+        storage.platform_fee_recipient = recipient;
+        
+        self.emit_event(Event::PlatformFeeRecipientUpdated { recipient })
+            .expect("Failed to emit PlatformFeeRecipientUpdated");
+    }
+    
+    /// Retirar las comisiones de plataforma acumuladas en una moneda
+    /// Se envían al destinatario de comisiones configurado
+    /// Solo admin
+    #[export]
+    pub async fn withdraw_platform_fees(&mut self, currency: Option<ActorId>) {
+        self.require_admin();
+        
+        let storage = self.get_mut();
+        let recipient = storage.platform_fee_recipient;
+        
+        // Descontar antes de enviar (previene doble retiro sin bloquear otras operaciones)
+        let amount = storage.platform_accrued.remove(&currency).unwrap_or_default();
+        if amount.is_zero() {
+            panic(TicketError::NothingToWithdraw);
+        }
+        
+        let sent = match currency {
+            None => send_value(recipient, amount.as_u128()).await,
+            Some(token) => vft_transfer(token, recipient, amount).await,
+        };
+        
+        // Si el envío es rechazado, las comisiones vuelven a quedar pendientes
+        // (sumadas a las acumuladas mientras se esperaba) y se informa el fallo
+        if !sent {
+            *storage.platform_accrued.entry(currency).or_default() += amount;
+            let _ = self.emit_event(Event::OperationFailed {
+                account: recipient,
+                error: TicketError::PaymentFailed,
+            });
+            return;
+        }
+        
+        self.emit_event(Event::PlatformFeesWithdrawn {
+            recipient,
+            currency,
+            amount,
+        })
+        .expect("Failed to emit PlatformFeesWithdrawn");
+    }
+    
+    /// Actualizar configuración de reventa de un evento
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn update_resale_config(
+        &mut self,
+        event_id: U256,
+        resale_config: ResaleConfig,
+    ) {
+        self.require_organizer();
+        
+        TicketService::validate_resale_config(&resale_config);
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        event_config.resale_config = resale_config;
+        
+        self.emit_event(Event::EventConfigUpdated { event_id })
+            .expect("Failed to emit EventConfigUpdated");
+    }
+    
+    /// Actualizar cuotas por billetera de un evento
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn update_wallet_limits(
+        &mut self,
+        event_id: U256,
+        wallet_limits: WalletLimits,
+    ) {
+        self.require_organizer();
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        event_config.wallet_limits = wallet_limits;
+        
+        self.emit_event(Event::EventConfigUpdated { event_id })
+            .expect("Failed to emit EventConfigUpdated");
+    }
+    
+    /// Actualizar configuración de comisiones de un evento
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn update_commission_config(
+        &mut self,
+        event_id: U256,
+        commission_config: CommissionConfig,
+    ) {
+        self.require_organizer();
+        
+        TicketService::validate_commission_config(&commission_config);
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        event_config.commission_config = commission_config;
+        
+        self.emit_event(Event::EventConfigUpdated { event_id })
+            .expect("Failed to emit EventConfigUpdated");
+    }
+    
+    /// Actualizar precio de venta primaria general de un evento
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn update_primary_pricing(
+        &mut self,
+        event_id: U256,
+        primary_price: Option<U256>,
+    ) {
+        self.require_organizer();
+        
+        TicketService::validate_primary_price(&primary_price);
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        event_config.primary_price = primary_price;
+        
+        self.emit_event(Event::EventConfigUpdated { event_id })
+            .expect("Failed to emit EventConfigUpdated");
+    }
+    
+    /// Actualizar la tabla de zonas de un evento
+    /// Las zonas existentes conservan su id y sus tickets minteados; una zona
+    /// con tickets no se puede quitar ni quedar con capacidad menor a lo minteado
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn update_zones(&mut self, event_id: U256, zones: Vec<ZoneConfig>) {
+        self.require_organizer();
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        TicketService::validate_zones(&zones, event_config.tickets_total);
+        
+        // No perder zonas con tickets emitidos
+        if event_config
+            .zones
+            .iter()
+            .any(|old| !old.minted.is_zero() && !zones.iter().any(|zone| zone.name == old.name))
+        {
+            panic(TicketError::InvalidInput);
+        }
+        
+        let mut next_id = event_config.zones.iter().map(|zone| zone.zone_id).max().unwrap_or(0);
+        let mut updated = Vec::new();
+        for zone in zones {
+            let (zone_id, minted, seats) = match event_config.zone(&zone.name) {
+                Some(old) => (old.zone_id, old.minted, old.seats.clone()),
+                None => {
+                    next_id += 1;
+                    (next_id, U256::zero(), Vec::new())
+                }
+            };
+            if zone.capacity < minted {
+                panic(TicketError::InvalidAmount);
+            }
+            // Con mapa de asientos, la capacidad es el número de asientos
+            if !seats.is_empty() && zone.capacity != U256::from(seats.len()) {
+                panic(TicketError::InvalidAmount);
+            }
+            updated.push(EventZone {
+                zone_id,
+                name: zone.name,
+                capacity: zone.capacity,
+                face_price: zone.face_price,
+                minted,
+                seats,
+            });
+        }
+        event_config.zones = updated;
+        
+        self.emit_event(Event::EventConfigUpdated { event_id })
+            .expect("Failed to emit EventConfigUpdated");
+    }
+    
+    /// Definir el mapa de asientos de una zona (en orden de preferencia)
+    /// Debe haber un asiento por cada plaza de la zona y ningún ticket emitido en ella
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn set_zone_seats(&mut self, event_id: U256, zone: String, seats: Vec<Seat>) {
+        self.require_organizer();
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        let event_zone = event_config.zone(&zone);
+        if event_zone.is_none() {
+            panic(TicketError::ZoneNotFound);
+        }
+        let event_zone = event_zone.unwrap();
+        
+        if !event_zone.minted.is_zero() {
+            panic(TicketError::InvalidInput);
+        }
+        
+        if !seats.is_empty() && U256::from(seats.len()) != event_zone.capacity {
+            panic(TicketError::InvalidAmount);
+        }
+        
+        // Asientos bien formados y únicos en todo el evento
+        for (i, seat) in seats.iter().enumerate() {
+            if seat.section.is_empty() || seat.row.is_empty() {
+                panic(TicketError::InvalidInput);
+            }
+            if seats[..i].contains(seat)
+                || event_config
+                    .zones
+                    .iter()
+                    .any(|other| other.name != zone && other.seats.contains(seat))
+            {
+                panic(TicketError::SeatTaken);
+            }
+        }
+        
+        if let Some(event_zone) = event_config.zones.iter_mut().find(|z| z.name == zone) {
+            event_zone.seats = seats;
+        }
+        
+        self.emit_event(Event::EventConfigUpdated { event_id })
+            .expect("Failed to emit EventConfigUpdated");
+    }
+    
+    /// Desactivar/activar evento
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn set_event_active(&mut self, event_id: U256, active: bool) {
+        self.require_organizer();
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        // La cancelación es terminal
+        if event_config.cancelled {
+            panic(TicketError::EventCancelled);
+        }
+        
+        event_config.active = active;
+        
+        // Un evento desactivado no admite compras de sus listados
+        if !active {
+            let events = storage.delist_event(event_id, ListingCancelReason::EventDisabled);
+            self.emit_events(events);
+        }
+        
+        self.emit_event(Event::EventConfigUpdated { event_id })
+            .expect("Failed to emit EventConfigUpdated");
+    }
+    
+    /// Habilitar/deshabilitar el fan exchange de un evento
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn set_fan_exchange(&mut self, event_id: U256, enabled: bool) {
+        self.require_organizer();
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        if event_config.cancelled {
+            panic(TicketError::EventCancelled);
+        }
+        
+        event_config.fan_exchange = enabled;
+        
+        self.emit_event(Event::EventConfigUpdated { event_id })
+            .expect("Failed to emit EventConfigUpdated");
+    }
+    
+    /// Cancelar un evento (terminal)
+    /// Congela transferencias y listados, y abre los reembolsos a los poseedores
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn cancel_event(&mut self, event_id: U256) {
+        self.require_organizer();
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get_mut(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        if event_config.cancelled {
+            panic(TicketError::EventCancelled);
+        }
+        
+        // Liberada la recaudación, el escrow ya no puede cubrir los reembolsos
+        if storage.primary_released.contains(&event_id) {
+            panic(TicketError::RevenueAlreadyReleased);
+        }
+        
+        event_config.cancelled = true;
+        event_config.active = false;
+        
+        // Retirar los listados del evento
+        let events = storage.delist_event(event_id, ListingCancelReason::EventCancelled);
+        self.emit_events(events);
+        
+        self.emit_event(Event::EventCancelled { event_id })
+            .expect("Failed to emit EventCancelled");
+    }
+    
+    /// Reclamar el reembolso de un ticket de un evento cancelado
+    /// Quema el NFT y acredita el valor facial al saldo reclamable del poseedor
+    /// Solo el propietario actual del ticket
+    #[export]
+    pub async fn claim_refund(&mut self, ticket_id: U256) {
+        self.non_reentrant();
+        
+        let holder = msg::source();
+        let storage = self.get_mut();
+        
+        let ticket = storage.tickets.get(&ticket_id);
+        if ticket.is_none() {
+            self.unlock();
+            panic(TicketError::TicketNotFound);
+        }
+        let ticket = ticket.unwrap().clone();
+        
+        if ticket.current_owner != holder {
+            self.unlock();
+            panic(TicketError::TicketNotOwned);
+        }
+        
+        if ticket.used {
+            self.unlock();
+            panic(TicketError::TicketAlreadyUsed);
+        }
+        
+        let event_config = storage.events.get(&ticket.event_id);
+        if event_config.is_none() {
+            self.unlock();
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        if !event_config.cancelled {
+            self.unlock();
+            panic(TicketError::EventNotCancelled);
+        }
+        
+        // El reembolso sale de la recaudación primaria en escrow; un ticket sin precio
+        // pagado on-chain (minteado con `mint_tickets`) no tiene nada que reembolsar
+        let amount = ticket.purchase_price;
+        if amount.is_zero() {
+            self.unlock();
+            panic(TicketError::NothingToRefund);
+        }
+        let escrowed = storage
+            .primary_escrow
+            .get(&ticket.event_id)
+            .copied()
+            .unwrap_or_default();
+        if amount > escrowed {
+            self.unlock();
+            panic(TicketError::InsufficientEscrow);
+        }
+        let currency = event_config.payment_token;
+        
+        // Quemar el NFT en VMT (si falla, el ticket y el escrow quedan intactos)
+        storage.pending_tickets.insert(ticket_id);
+        if !burn_ticket_nft(storage.vmt_contract_id, holder, ticket_id).await {
+            self.abort(holder, TicketError::BurnFailed);
+            return;
+        }
+        
+        // Retirar el ticket y descontar el escrow
+        storage.tickets.remove(&ticket_id);
+        if let Some(tickets) = storage.event_tickets.get_mut(&ticket.event_id) {
+            tickets.retain(|id| *id != ticket_id);
+        }
+        if let Some(seat) = ticket.seat.clone() {
+            storage.seat_assignments.remove(&(ticket.event_id, seat));
+        }
+        storage.primary_escrow.insert(ticket.event_id, escrowed - amount);
+        storage.credit(holder, currency, amount);
+        storage.move_holding(ticket.event_id, Some(holder), None);
+        
+        self.emit_event(Event::TicketRefunded {
+            ticket_id,
+            event_id: ticket.event_id,
+            holder,
+            amount,
+        })
+        .expect("Failed to emit TicketRefunded");
+        
+        self.unlock();
+    }
+    
+    /// Liberar la recaudación primaria al organizador una vez iniciado el evento
+    /// Solo organizador del evento o admin
+    #[export]
+    pub fn release_primary_revenue(&mut self, event_id: U256) {
+        self.require_organizer();
+        
+        let storage = self.get_mut();
+        
+        let event_config = storage.events.get(&event_id);
+        if event_config.is_none() {
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar permisos
+        let caller = msg::source();
+        if caller != storage.admin && caller != event_config.organizer {
+            panic(TicketError::Unauthorized);
+        }
+        
+        if event_config.cancelled {
+            panic(TicketError::EventCancelled);
+        }
+        
+        if self.current_timestamp() < event_config.event_start_time {
+            panic(TicketError::EventNotStarted);
+        }
+        
+        let organizer = event_config.organizer;
+        let currency = event_config.payment_token;
+        let amount = storage.primary_escrow.remove(&event_id).unwrap_or_default();
+        storage.primary_released.insert(event_id);
+        storage.credit(organizer, currency, amount);
+        
+        self.emit_event(Event::PrimaryRevenueReleased {
+            event_id,
+            organizer,
+            amount,
+        })
+        .expect("Failed to emit PrimaryRevenueReleased");
+    }
+    
+    /// Obtener la recaudación primaria en escrow de un evento
+    #[export]
+    pub fn get_primary_escrow(&self, event_id: U256) -> U256 {
+        self.get()
+            .primary_escrow
+            .get(&event_id)
+            .copied()
+            .unwrap_or_default()
+    }
+    
+    /// Obtener información de un ticket
+    #[export]
+    pub fn get_ticket(&self, ticket_id: U256) -> Option<Ticket> {
+        self.get().tickets.get(&ticket_id).cloned()
+    }
+    
+    /// Obtener configuración de un evento
+    #[export]
+    pub fn get_event(&self, event_id: U256) -> Option<EventConfig> {
+        self.get().events.get(&event_id).cloned()
+    }
+    
+    /// Obtener tickets de un evento
+    #[export]
+    pub fn get_event_tickets(&self, event_id: U256) -> Vec<U256> {
+        self.get()
+            .event_tickets
+            .get(&event_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+    
+    /// Obtener tickets de un usuario
+    #[export]
+    pub fn get_user_tickets(&self, user: ActorId) -> Vec<Ticket> {
+        self.get()
+            .tickets
+            .iter()
+            .filter(|(_, ticket)| ticket.current_owner == user)
+            .map(|(_, ticket)| ticket.clone())
+            .collect()
+    }
+    
+    /// Verificar si una dirección es organizador
+    #[export]
+    pub fn is_organizer(&self, address: ActorId) -> bool {
+        let storage = self.get();
+        storage.organizers.contains(&address) || address == storage.admin
+    }
+    
+    /// Verificar si una dirección es escáner
+    #[export]
+    pub fn is_scanner(&self, address: ActorId) -> bool {
+        let storage = self.get();
+        storage.scanners.contains(&address) || address == storage.admin
+    }
+    
+    /// Obtener comisiones de plataforma en una moneda (total histórico y pendiente de retiro)
+    #[export]
+    pub fn get_platform_revenue(&self, currency: Option<ActorId>) -> PlatformRevenue {
+        let storage = self.get();
+        PlatformRevenue {
+            currency,
+            total: storage
+                .platform_revenue_total
+                .get(&currency)
+                .copied()
+                .unwrap_or_default(),
+            accrued: storage
+                .platform_accrued
+                .get(&currency)
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+    
+    /// Obtener comisiones de plataforma acumuladas por un evento (en la moneda del evento)
+    #[export]
+    pub fn get_event_platform_revenue(&self, event_id: U256) -> U256 {
+        self.get()
+            .platform_revenue_by_event
+            .get(&event_id)
+            .copied()
+            .unwrap_or_default()
+    }
+    
+    /// Obtener la tabla de zonas de un evento
+    #[export]
+    pub fn get_event_zones(&self, event_id: U256) -> Vec<EventZone> {
+        self.get()
+            .events
+            .get(&event_id)
+            .map(|event_config| event_config.zones.clone())
+            .unwrap_or_default()
+    }
+    
+    /// Obtener los tickets disponibles de una zona (None si no existe)
+    #[export]
+    pub fn get_zone_availability(&self, event_id: U256, zone: String) -> Option<U256> {
+        self.get()
+            .events
+            .get(&event_id)
+            .and_then(|event_config| event_config.zone(&zone))
+            .map(|zone| zone.capacity - zone.minted)
+    }
+    
+    /// Obtener los asientos libres de una zona en orden de preferencia
+    #[export]
+    pub fn get_free_seats(&self, event_id: U256, zone: String) -> Vec<Seat> {
+        let storage = self.get();
+        storage
+            .events
+            .get(&event_id)
+            .and_then(|event_config| event_config.zone(&zone))
+            .map(|zone| {
+                zone.seats
+                    .iter()
+                    .filter(|seat| !storage.seat_assignments.contains_key(&(event_id, (*seat).clone())))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    
+    /// Obtener los asientos que asignaría el mejor disponible para `count` tickets
+    /// (vacío si la zona no tiene mapa de asientos o no quedan suficientes)
+    #[export]
+    pub fn get_best_available(&self, event_id: U256, zone: String, count: u32) -> Vec<Seat> {
+        let free = self.get_free_seats(event_id, zone);
+        let free: Vec<&Seat> = free.iter().collect();
+        best_available(&free, count as usize).into_iter().cloned().collect()
+    }
+    
+    /// Obtener el ticket emitido para un asiento
+    #[export]
+    pub fn get_seat_ticket(&self, event_id: U256, seat: Seat) -> Option<U256> {
+        self.get().seat_assignments.get(&(event_id, seat)).copied()
+    }
+    
+    /// Obtener estadísticas de un evento
+    #[export]
+    pub fn get_event_stats(&self, event_id: U256) -> Option<EventStats> {
+        let storage = self.get();
+        let event_config = storage.events.get(&event_id)?;
+        let tickets = storage.event_tickets.get(&event_id)?;
+        
+        let used_tickets = tickets.iter()
+            .filter_map(|tid| storage.tickets.get(tid))
+            .filter(|t| t.used)
+            .count();
+        
+        Some(EventStats {
+            event_id,
+            tickets_total: event_config.tickets_total,
+            tickets_minted: event_config.tickets_minted,
+            tickets_used: used_tickets as u64,
+            active: event_config.active,
+        })
+    }
+    
+    /// Obtener estadísticas financieras de un evento
+    /// Se mantienen incrementalmente, sin recorrer los tickets
+    #[export]
+    pub fn get_event_financials(&self, event_id: U256) -> Option<EventFinancials> {
+        let storage = self.get();
+        storage.events.get(&event_id)?;
+        let mut financials = storage
+            .event_financials
+            .get(&event_id)
+            .cloned()
+            .unwrap_or(EventFinancials {
+                event_id,
+                ..Default::default()
+            });
+        financials.floor_listing_price = storage.floor_listing_price(event_id, self.current_timestamp());
+        Some(financials)
+    }
+    
+    /// Obtener estado completo del contrato
+    #[export]
+    pub fn get_storage(&self) -> State {
+        let storage = self.get();
+        let current_time = self.current_timestamp();
+        let mut state: State = storage.clone().into();
+        for (event_id, financials) in state.event_financials.iter_mut() {
+            financials.floor_listing_price = storage.floor_listing_price(*event_id, current_time);
+        }
+        state
+    }
+}
+
+/// Servicio de Marketplace (separado del servicio de Tickets)
+struct MarketService(());
+
+impl MarketService {
+    pub fn new() -> Self {
+        Self(())
+    }
+    
+    pub fn get_mut(&mut self) -> &'static mut Storage {
+        unsafe { STORAGE.as_mut().expect("Storage is not initialized") }
+    }
+    
+    pub fn get(&self) -> &'static Storage {
+        unsafe { STORAGE.as_ref().expect("Storage is not initialized") }
+    }
+    
+    /// Guard contra reentrancy
+    fn non_reentrant(&mut self) {
+        let storage = self.get_mut();
+        if storage.locked {
+            panic(TicketError::ReentrancyDetected);
+        }
+        storage.locked = true;
+    }
+    
+    /// Libera el lock y los tickets reservados por la operación en curso
+    fn unlock(&mut self) {
+        let storage = self.get_mut();
+        storage.locked = false;
+        storage.pending_tickets.clear();
+    }
+    
+    /// Valida que `seller` pueda poner `ticket_id` a la venta a `price`
+    /// Devuelve el event_id del ticket
+    fn validate_listing(
+        &self,
+        seller: ActorId,
+        ticket_id: U256,
+        price: U256,
+        current_time: u64,
+    ) -> Result<U256, TicketError> {
+        let storage = self.get();
+        
+        // Validar que ninguna operación en curso pueda restaurar la venta del ticket
+        storage.check_not_pending(&ticket_id)?;
+        
+        // Validar que no esté ya listado, en subasta ni en un paquete
+        if storage.is_on_sale(&ticket_id) {
+            return Err(TicketError::TicketAlreadyListed);
+        }
+        
+        // Validar que el ticket existe
+        let ticket = storage.tickets.get(&ticket_id).ok_or(TicketError::TicketNotFound)?;
+        
+        // Validar que no esté usado
+        if ticket.used {
+            return Err(TicketError::TicketAlreadyUsed);
+        }
+        
+        // Validar propiedad
+        if ticket.current_owner != seller {
+            return Err(TicketError::TicketNotOwned);
+        }
+        
+        // Validar evento, reventa habilitada, precio máximo y ventana de tiempo
+        let event_config = storage
+            .events
+            .get(&ticket.event_id)
+            .ok_or(TicketError::EventNotFound)?;
+        event_config.check_resale(price, ticket, current_time)?;
+        
+        // Validar la cuota de reventas del vendedor
+        let resold = storage.resales.get(&(ticket.event_id, seller)).copied().unwrap_or_default();
+        event_config.wallet_limits.check_resale(resold)?;
+        
+        Ok(ticket.event_id)
+    }
+    
+    /// Obtiene timestamp actual
+fn current_timestamp(&self) -> u64 {
+    exec::block_timestamp()
+}
+}
+
+#[service(events = Event)]
+impl MarketService {
+    // Auxiliares que emiten eventos: sails genera `emit_event` solo para los
+    // métodos del bloque `#[service]`
+    /// Termina una operación que falló después de un `await`: el caller ya restauró
+    /// el estado y devolvió los fondos. Emite `OperationFailed` y libera el lock sin
+    /// entrar en pánico (un pánico en este punto no revierte lo hecho antes del `await`)
+    fn abort(&mut self, account: ActorId, error: TicketError) {
+        let _ = self.emit_event(Event::OperationFailed { account, error });
+        self.unlock();
+    }
+    
+    /// Emite los eventos devueltos por una operación de `Storage`
+    fn emit_events(&mut self, events: Vec<Event>) {
+        for event in events {
+            self.emit_event(event).expect("Failed to emit event");
+        }
+    }
+    
+    /// Fan exchange: si el listado está a precio de lista en un evento con el modo
+    /// habilitado, vende el ticket al primer comprador elegible de la lista de espera
+    /// usando su depósito en escrow. Devuelve si hubo asignación.
+    async fn match_waitlist(&mut self, ticket_id: U256) -> bool {
+        let storage = self.get_mut();
+        
+        let listing = match storage.listings.get(&ticket_id) {
+            Some(listing) if listing.kind == ListingKind::FixedPrice && listing.is_public() => listing.clone(),
+            _ => return false,
+        };
+        let ticket = match storage.tickets.get(&ticket_id) {
+            Some(ticket) => ticket,
+            None => return false,
+        };
+        let event_config = match storage.events.get(&listing.event_id) {
+            Some(event_config) => event_config,
+            None => return false,
+        };
+        
+        // Solo se asignan listados a precio de lista
+        if !event_config.fan_exchange || event_config.primary_price_for(&ticket.zone) != Some(listing.price) {
+            return false;
+        }
+        
+        // Primer comprador de la fila con depósito suficiente y dentro de sus cuotas
+        let key = (listing.event_id, ticket.zone.clone());
+        let price = listing.price;
+        let currency = event_config.payment_token;
+        let position = storage.waitlists.get(&key).and_then(|queue| {
+            queue.iter().position(|entry| {
+                entry.buyer != listing.seller
+                    && entry.deposit >= price
+                    && storage
+                        .check_purchase_limits(entry.buyer, &[(listing.event_id, listing.seller)])
+                        .is_ok()
+            })
+        });
+        let Some(position) = position else {
+            return false;
+        };
+        let entry = storage.remove_waitlist_entry(&key, position);
+        
+        // Asignar por el camino de venta habitual
+        // (si la transferencia falla, el listado y el lugar en la fila se restauran)
+        let listing = storage.remove_listing(&ticket_id).unwrap_or(listing);
+        storage.pending_tickets.insert(ticket_id);
+        if !transfer_ticket_nft(storage.vmt_contract_id, listing.seller, entry.buyer, ticket_id).await {
+            let buyer = entry.buyer;
+            let event_id = listing.event_id;
+            storage.insert_listing(listing);
+            storage.waitlists.entry(key).or_default().insert(position, entry);
+            let events = storage.delist_if_closed(ticket_id, event_id);
+            self.emit_events(events);
+            let _ = self.emit_event(Event::OperationFailed {
+                account: buyer,
+                error: TicketError::TransferFailed,
+            });
+            return false;
+        }
+        let split = storage.settle_resale(ticket_id, entry.buyer, price);
+        
+        // Devolver el excedente del depósito (si el precio de lista bajó desde que se sumó)
+        refund_escrow(storage, currency, entry.buyer, entry.deposit - price).await;
+        
+        self.emit_event(Event::TicketSold {
+            ticket_id,
+            event_id: listing.event_id,
+            seller: listing.seller,
+            buyer: entry.buyer,
+            price,
+            seller_share: split.seller_share,
+            organizer_share: split.organizer_share,
+            platform_share: split.platform_share,
+            beneficiary_shares: split.beneficiary_shares,
+            rounding_remainder: split.rounding_remainder,
+        })
+        .expect("Failed to emit TicketSold");
+        
+        true
+    }
+    
+    /// Listar un ticket para reventa en el Marketplace
+    #[export]
+    pub async fn list_ticket(
+        &mut self,
+        ticket_id: U256,
+        price: U256,
+        expires_at: Option<u64>,
+        reserved_for: Option<ActorId>,
+    ) {
+        self.non_reentrant();
+        
+        let seller = msg::source();
+        
+        if price == U256::zero() {
+            self.unlock();
+            panic(TicketError::InvalidPrice);
+        }
+        
+        if seller == ZERO_ID || reserved_for == Some(ZERO_ID) || reserved_for == Some(seller) {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        
+        let current_time = self.current_timestamp();
+        if expires_at.is_some_and(|expires_at| expires_at <= current_time) {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        
+        let event_id = match self.validate_listing(seller, ticket_id, price, current_time) {
+            Ok(event_id) => event_id,
+            Err(e) => {
+                self.unlock();
+                panic(e);
+            }
+        };
+        
+        let storage = self.get_mut();
+        
+        // Crear listado
+        let listing = Listing {
+            ticket_id,
+            seller,
+            price,
+            listed_at: current_time,
+            event_id,
+            kind: ListingKind::FixedPrice,
+            expires_at,
+            reserved_for,
+        };
+        
+        storage.insert_listing(listing);
+        
+        self.emit_event(Event::TicketListed {
+            ticket_id,
+            event_id,
+            seller,
+            price,
+            reserved_for,
+        })
+        .expect("Failed to emit TicketListed");
+        
+        // Fan exchange: un listado a precio de lista se asigna a la lista de espera
+        self.match_waitlist(ticket_id).await;
+        
+        self.unlock();
+    }
+    
+    /// Listar un ticket en subasta holandesa: el precio baja linealmente desde
+    /// `start_price` hasta `floor_price` entre el momento del listado y `ends_at`
+    #[export]
+    pub fn list_ticket_dutch(
+        &mut self,
+        ticket_id: U256,
+        start_price: U256,
+        floor_price: U256,
+        ends_at: u64,
+    ) {
+        let seller = msg::source();
+        
+        if floor_price == U256::zero() || floor_price > start_price {
+            panic(TicketError::InvalidPrice);
+        }
+        
+        let current_time = self.current_timestamp();
+        if ends_at <= current_time {
+            panic(TicketError::InvalidInput);
+        }
+        
+        // El precio baja de forma monótona: si el inicial y el final respetan los límites
+        // de reventa (max_price, min_price y recargo máximo), todo el recorrido los respeta
+        let event_id = match self
+            .validate_listing(seller, ticket_id, start_price, current_time)
+            .and_then(|_| self.validate_listing(seller, ticket_id, floor_price, current_time))
+        {
+            Ok(event_id) => event_id,
+            Err(e) => panic(e),
+        };
+        
+        let storage = self.get_mut();
+        storage.insert_listing(Listing {
+            ticket_id,
+            seller,
+            price: start_price,
+            listed_at: current_time,
+            event_id,
+            kind: ListingKind::Dutch {
+                floor_price,
+                ends_at,
+            },
+            expires_at: None,
+            reserved_for: None,
+        });
+        
+        self.emit_event(Event::TicketListedDutch {
+            ticket_id,
+            event_id,
+            seller,
+            start_price,
+            floor_price,
+            ends_at,
+        })
+        .expect("Failed to emit TicketListedDutch");
+    }
+    
+    /// Comprar un ticket listado en el Marketplace
+    /// Pagable: en VARA el mensaje debe incluir al menos el precio del listado;
+    /// en eventos con token, el pagador debe haber aprobado el precio al contrato
+    #[export]
+    pub async fn buy_ticket(&mut self, buyer: ActorId, ticket_id: U256) {
+        self.non_reentrant();
+        
+        if buyer == ZERO_ID {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        
+        let storage = self.get_mut();
+        let current_time = self.current_timestamp();
+        
+        // Validar listado, ticket, evento y precio vigente
+        let (listing, price, currency) = match storage.check_purchase(buyer, ticket_id, current_time) {
+            Ok(purchase) => purchase,
+            Err(e) => {
+                self.unlock();
+                panic(e);
+            }
+        };
+        
+        // Validar cuotas por billetera
+        if let Err(e) = storage.check_purchase_limits(buyer, &[(listing.event_id, listing.seller)]) {
+            self.unlock();
+            panic(e);
+        }
+        
+        // Validar el pago recibido (VARA nativo si el evento no liquida en token)
+        let payment = msg::value();
+        if currency.is_none() && U256::from(payment) < price {
+            self.unlock();
+            panic(TicketError::InsufficientPayment);
+        }
+        
+        // Guardar datos antes de remover el listado
+        let payer = msg::source();
+        let seller = listing.seller;
+        let event_id = listing.event_id;
+        
+        // Remover el listado antes de cobrar y transferir (previene doble compra)
+        let listing = storage.remove_listing(&ticket_id).unwrap_or(listing);
+        storage.pending_tickets.insert(ticket_id);
+        
+        // Si el evento liquida en token, cobrar el precio al pagador
+        let mut failure = None;
+        if let Some(token) = currency {
+            if !vft_transfer_from(token, payer, exec::program_id(), price).await {
+                failure = Some(TicketError::PaymentFailed);
+            }
+        }
+        
+        // Transferir el NFT del vendedor al comprador usando VMT
+        if failure.is_none() && !transfer_ticket_nft(storage.vmt_contract_id, seller, buyer, ticket_id).await {
+            failure = Some(TicketError::TransferFailed);
+        }
+        
+        // Si el cobro o la transferencia fallaron, restaurar el listado y devolver lo pagado
+        if let Some(error) = failure {
+            storage.insert_listing(listing);
+            let events = storage.delist_if_closed(ticket_id, event_id);
+            self.emit_events(events);
+            let collected = if error == TicketError::TransferFailed { price } else { U256::zero() };
+            storage.return_payment(payer, currency, collected, payment).await;
+            self.abort(payer, error);
+            return;
+        }
+        
+        // Actualizar propietario y acreditar el pago a vendedor, organizador y plataforma
+        let split = storage.settle_resale(ticket_id, buyer, price);
+        
+        // Devolver el excedente al pagador (todo el VARA adjunto si se pagó en token)
+        let refund = if currency.is_none() { payment - price.as_u128() } else { payment };
+        storage.refund_value(payer, refund).await;
+        
+        self.emit_event(Event::TicketSold {
+            ticket_id,
+            event_id,
+            seller,
+            buyer,
+            price,
+            seller_share: split.seller_share,
+            organizer_share: split.organizer_share,
+            platform_share: split.platform_share,
+            beneficiary_shares: split.beneficiary_shares,
+            rounding_remainder: split.rounding_remainder,
+        })
+        .expect("Failed to emit TicketSold");
+        
+        self.unlock();
+    }
+    
+    /// Comprar varios tickets listados de un mismo vendedor en un solo mensaje
+    /// Todo o nada: los NFTs se transfieren en un único lote; si falla, ningún ticket
+    /// se vende, los listados se restauran y el pago se devuelve
+    /// Todos los listados deben liquidarse en la misma moneda; el pago cubre la suma de precios
+    #[export]
+    pub async fn buy_tickets(&mut self, buyer: ActorId, ticket_ids: Vec<U256>) {
+        self.non_reentrant();
+        
+        if buyer == ZERO_ID || ticket_ids.is_empty() {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        
+        let storage = self.get_mut();
+        let current_time = self.current_timestamp();
+        
+        // Validar cada compra antes de mover fondos o tickets
+        let mut purchases: Vec<(Listing, U256)> = Vec::new();
+        let mut currency: Option<ActorId> = None;
+        let mut total = U256::zero();
+        for (i, ticket_id) in ticket_ids.iter().enumerate() {
+            // Rechazar tickets repetidos
+            if ticket_ids[..i].contains(ticket_id) {
+                self.unlock();
+                panic(TicketError::InvalidInput);
+            }
+            
+            let (listing, price, listing_currency) =
+                match storage.check_purchase(buyer, *ticket_id, current_time) {
+                    Ok(purchase) => purchase,
+                    Err(e) => {
+                        self.unlock();
+                        panic(e);
+                    }
+                };
+            
+            // Un único pago y una única transferencia por lote:
+            // todos los listados deben usar la misma moneda y ser del mismo vendedor
+            if i == 0 {
+                currency = listing_currency;
+            } else if listing_currency != currency || listing.seller != purchases[0].0.seller {
+                self.unlock();
+                panic(TicketError::InvalidInput);
+            }
+            
+            total += price;
+            purchases.push((listing, price));
+        }
+        
+        // Validar cuotas por billetera sobre el conjunto de compras
+        let limit_keys: Vec<(U256, ActorId)> = purchases
+            .iter()
+            .map(|(listing, _)| (listing.event_id, listing.seller))
+            .collect();
+        if let Err(e) = storage.check_purchase_limits(buyer, &limit_keys) {
+            self.unlock();
+            panic(e);
+        }
+        
+        // Validar el pago combinado (VARA nativo si los eventos no liquidan en token)
+        let payment = msg::value();
+        if currency.is_none() && U256::from(payment) < total {
+            self.unlock();
+            panic(TicketError::InsufficientPayment);
+        }
+        
+        // Remover todos los listados antes de cobrar y transferir (previene doble compra)
+        for (listing, _) in purchases.iter() {
+            storage.remove_listing(&listing.ticket_id);
+            storage.pending_tickets.insert(listing.ticket_id);
+        }
+        
+        // Si los eventos liquidan en token, cobrar el total al pagador
+        let payer = msg::source();
+        let mut failure = None;
+        if let Some(token) = currency {
+            if !vft_transfer_from(token, payer, exec::program_id(), total).await {
+                failure = Some(TicketError::PaymentFailed);
+            }
+        }
+        
+        // Transferir todos los NFTs del vendedor al comprador en un único lote de VMT
+        let seller = purchases[0].0.seller;
+        let transfer_ids: Vec<U256> = purchases.iter().map(|(listing, _)| listing.ticket_id).collect();
+        if failure.is_none() && !transfer_ticket_nfts(storage.vmt_contract_id, seller, buyer, &transfer_ids).await {
+            failure = Some(TicketError::TransferFailed);
+        }
+        
+        // Si el cobro o la transferencia fallaron, ninguna compra se ejecuta:
+        // los listados se restauran y se devuelve lo pagado
+        if let Some(error) = failure {
+            for (listing, _) in purchases {
+                let (ticket_id, event_id) = (listing.ticket_id, listing.event_id);
+                storage.insert_listing(listing);
+                let events = storage.delist_if_closed(ticket_id, event_id);
+                self.emit_events(events);
+            }
+            let collected = if error == TicketError::TransferFailed { total } else { U256::zero() };
+            storage.return_payment(payer, currency, collected, payment).await;
+            self.abort(payer, error);
+            return;
+        }
+        
+        // Actualizar propietarios y acreditar cada pago a vendedor, organizador y plataforma
+        for (listing, price) in purchases {
+            let split = storage.settle_resale(listing.ticket_id, buyer, price);
+            
+            self.emit_event(Event::TicketSold {
+                ticket_id: listing.ticket_id,
+                event_id: listing.event_id,
+                seller,
+                buyer,
+                price,
+                seller_share: split.seller_share,
+                organizer_share: split.organizer_share,
+                platform_share: split.platform_share,
+                beneficiary_shares: split.beneficiary_shares,
+                rounding_remainder: split.rounding_remainder,
+            })
+            .expect("Failed to emit TicketSold");
+        }
+        
+        // Devolver el excedente al pagador (todo el VARA adjunto si se pagó en token)
+        let refund = if currency.is_none() { payment - total.as_u128() } else { payment };
+        storage.refund_value(payer, refund).await;
+        
+        self.unlock();
+    }
+    
+    /// Listar varios tickets de un mismo evento como un paquete con un único precio
+    /// Los tickets del paquete no se pueden listar por separado
+    #[export]
+    pub fn list_bundle(&mut self, ticket_ids: Vec<U256>, price: U256) {
+        let seller = msg::source();
+        
+        if price == U256::zero() {
+            panic(TicketError::InvalidPrice);
+        }
+        
+        // Un paquete tiene al menos dos tickets, sin repetidos
+        if ticket_ids.len() < 2 {
+            panic(TicketError::InvalidInput);
+        }
+        for (i, ticket_id) in ticket_ids.iter().enumerate() {
+            if ticket_ids[..i].contains(ticket_id) {
+                panic(TicketError::InvalidInput);
+            }
+        }
+        
+        // Validar cada ticket con su parte del precio
+        let current_time = self.current_timestamp();
+        let prices = bundle_prices(price, ticket_ids.len());
+        let mut event_id = U256::zero();
+        for (i, (ticket_id, ticket_price)) in ticket_ids.iter().zip(prices.iter()).enumerate() {
+            let ticket_event_id = match self.validate_listing(seller, *ticket_id, *ticket_price, current_time) {
+                Ok(event_id) => event_id,
+                Err(e) => panic(e),
+            };
+            
+            // Todos los tickets deben ser del mismo evento
+            if i == 0 {
+                event_id = ticket_event_id;
+            } else if ticket_event_id != event_id {
+                panic(TicketError::InvalidInput);
+            }
+        }
+        
+        let storage = self.get_mut();
+        storage.bundle_id_counter += U256::one();
+        let bundle_id = storage.bundle_id_counter;
+        storage.insert_bundle(Bundle {
+            bundle_id,
+            event_id,
+            seller,
+            ticket_ids: ticket_ids.clone(),
+            price,
+            listed_at: current_time,
+        });
+        
+        self.emit_event(Event::BundleListed {
+            bundle_id,
+            event_id,
+            seller,
+            ticket_ids,
+            price,
+        })
+        .expect("Failed to emit BundleListed");
+    }
+    
+    /// Comprar un paquete completo
+    /// Pagable: en VARA el mensaje debe incluir al menos el precio del paquete;
+    /// en eventos con token, el pagador debe haber aprobado el precio al contrato
+    #[export]
+    pub async fn buy_bundle(&mut self, bundle_id: U256) {
+        self.non_reentrant();
+        
+        let buyer = msg::source();
+        let current_time = self.current_timestamp();
+        let storage = self.get_mut();
+        
+        let bundle = storage.bundles.get(&bundle_id);
+        if bundle.is_none() {
+            self.unlock();
+            panic(TicketError::BundleNotFound);
+        }
+        let bundle = bundle.unwrap().clone();
+        
+        if buyer == bundle.seller {
+            self.unlock();
+            panic(TicketError::InvalidInput);
+        }
+        
+        let event_config = storage.events.get(&bundle.event_id);
+        if event_config.is_none() {
+            self.unlock();
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
+        
+        // Validar que el paquete no haya vencido
+        if bundle.is_expired(event_config, current_time) {
+            self.unlock();
+            panic(TicketError::ListingExpired);
+        }
+        
+        // Validar cada ticket con su parte del precio
+        let prices = bundle_prices(bundle.price, bundle.ticket_ids.len());
+        for (ticket_id, ticket_price) in bundle.ticket_ids.iter().zip(prices.iter()) {
+            let ticket = storage.tickets.get(ticket_id);
+            if ticket.is_none() {
+                self.unlock();
+                panic(TicketError::TicketNotFound);
+            }
+            let ticket = ticket.unwrap();
+            
+            if ticket.used {
+                self.unlock();
+                panic(TicketError::TicketAlreadyUsed);
+            }
+            
+            if ticket.current_owner != bundle.seller {
+                self.unlock();
+                panic(TicketError::TicketNotOwned);
+            }
+            
+            if let Err(e) = event_config.check_resale(*ticket_price, ticket, current_time) {
+                self.unlock();
+                panic(e);
+            }
+        }
+        
+        // Validar cuotas por billetera sobre todo el paquete
+        let limit_keys: Vec<(U256, ActorId)> = bundle
+            .ticket_ids
+            .iter()
+            .map(|_| (bundle.event_id, bundle.seller))
+            .collect();
+        if let Err(e) = storage.check_purchase_limits(buyer, &limit_keys) {
+            self.unlock();
+            panic(e);
         }
-    }
-    
-    /// Valida configuración de reventa
-    fn validate_resale_config(config: &ResaleConfig) {
-        if let (Some(start), Some(end)) = (config.resale_start_time, config.resale_end_time) {
-            if start >= end {
-                panic(TicketError::InvalidResaleConfig);
+        
+        // Validar el pago recibido (VARA nativo si el evento no liquida en token)
+        let currency = event_config.payment_token;
+        let payment = msg::value();
+        if currency.is_none() && U256::from(payment) < bundle.price {
+            self.unlock();
+            panic(TicketError::InsufficientPayment);
+        }
+        
+        // Remover el paquete antes de cobrar y transferir (previene doble compra)
+        let bundle = storage.remove_bundle(&bundle_id).unwrap_or(bundle);
+        storage.pending_tickets.extend(bundle.ticket_ids.iter().copied());
+        
+        // Si el evento liquida en token, cobrar el precio al pagador
+        let mut failure = None;
+        if let Some(token) = currency {
+            if !vft_transfer_from(token, buyer, exec::program_id(), bundle.price).await {
+                failure = Some(TicketError::PaymentFailed);
             }
         }
+        
+        // Transferir todos los NFTs en una sola operación: el paquete se vende entero o no se vende
+        if failure.is_none()
+            && !transfer_ticket_nfts(storage.vmt_contract_id, bundle.seller, buyer, &bundle.ticket_ids).await
+        {
+            failure = Some(TicketError::TransferFailed);
+        }
+        
+        // Si el cobro o la transferencia fallaron, restaurar el paquete y devolver lo pagado
+        if let Some(error) = failure {
+            let collected = if error == TicketError::TransferFailed { bundle.price } else { U256::zero() };
+            storage.return_payment(buyer, currency, collected, payment).await;
+            let (ticket_id, event_id) = (bundle.ticket_ids[0], bundle.event_id);
+            storage.insert_bundle(bundle);
+            let events = storage.delist_if_closed(ticket_id, event_id);
+            self.emit_events(events);
+            self.abort(buyer, error);
+            return;
+        }
+        
+        // Liquidar cada ticket con su parte del precio (comisión por ticket)
+        for (ticket_id, ticket_price) in bundle.ticket_ids.iter().zip(prices) {
+            let split = storage.settle_resale(*ticket_id, buyer, ticket_price);
+            
+            self.emit_event(Event::TicketSold {
+                ticket_id: *ticket_id,
+                event_id: bundle.event_id,
+                seller: bundle.seller,
+                buyer,
+                price: ticket_price,
+                seller_share: split.seller_share,
+                organizer_share: split.organizer_share,
+                platform_share: split.platform_share,
+                beneficiary_shares: split.beneficiary_shares,
+                rounding_remainder: split.rounding_remainder,
+            })
+            .expect("Failed to emit TicketSold");
+        }
+        
+        // Devolver el excedente al pagador (todo el VARA adjunto si se pagó en token)
+        let refund = if currency.is_none() { payment - bundle.price.as_u128() } else { payment };
+        storage.refund_value(buyer, refund).await;
+        
+        self.emit_event(Event::BundleSold {
+            bundle_id,
+            event_id: bundle.event_id,
+            seller: bundle.seller,
+            buyer,
+            price: bundle.price,
+        })
+        .expect("Failed to emit BundleSold");
+        
+        self.unlock();
     }
     
-    /// Obtiene timestamp actual
-fn current_timestamp(&self) -> u64 {
-    exec::block_timestamp()
-}
-}
-
-#[service(events = Event)]
-impl TicketService {
-    /// Crear un nuevo evento
-    /// Solo admin u organizador autorizado
+    /// Cancelar un paquete propio
     #[export]
-    pub fn create_event(
-        &mut self,
-        organizer: ActorId,
-        metadata_hash: [u8; 32],
-        event_start_time: u64,
-        tickets_total: U256,
-        resale_config: ResaleConfig,
-        commission_config: CommissionConfig,
-    ) {
-        self.require_organizer();
+    pub fn cancel_bundle(&mut self, bundle_id: U256) {
+        let seller = msg::source();
+        let storage = self.get_mut();
         
-        if organizer == ZERO_ID {
-            panic(TicketError::InvalidInput);
+        let bundle = storage.bundles.get(&bundle_id);
+        if bundle.is_none() {
+            panic(TicketError::BundleNotFound);
         }
+        let bundle = bundle.unwrap();
         
-        if tickets_total == U256::zero() {
-            panic(TicketError::InvalidAmount);
+        if bundle.seller != seller {
+            panic(TicketError::TicketNotOwned);
         }
         
-        TicketService::validate_resale_config(&resale_config);
-        TicketService::validate_commission_config(&commission_config);
+        let event_id = bundle.event_id;
+        storage.remove_bundle(&bundle_id);
         
+        self.emit_event(Event::BundleCancelled {
+            bundle_id,
+            event_id,
+            seller,
+            reason: ListingCancelReason::SellerCancelled,
+        })
+        .expect("Failed to emit BundleCancelled");
+    }
+    
+    /// Cancelar un listado activo
+    #[export]
+    pub fn cancel_listing(&mut self, ticket_id: U256) {
+        let seller = msg::source();
         let storage = self.get_mut();
-        storage.event_id_counter += U256::one();
-        let event_id = storage.event_id_counter;
         
-        let event_config = EventConfig {
-            event_id,
-            organizer,
-            metadata_hash,
-            event_start_time,
-            tickets_minted: U256::zero(),
-            tickets_total,
-            resale_config,
-            commission_config,
-            active: true,
-        };
+        // Obtener listado
+        let listing = storage.listings.get(&ticket_id);
+        if listing.is_none() {
+            panic(TicketError::ListingNotFound);
+        }
+        let listing = listing.unwrap();
         
-        storage.events.insert(event_id, event_config.clone());
-        storage.event_tickets.insert(event_id, Vec::new());
+        // Validar propiedad del listado
+        if listing.seller != seller {
+            panic(TicketError::TicketNotOwned);
+        }
         
-        // Si el organizador no está en la lista, agregarlo
-        storage.organizers.insert(organizer);
+        // Remover listado
+        let event_id = listing.event_id;
+        storage.remove_listing(&ticket_id);
         
-        self.emit_event(Event::EventCreated {
+        self.emit_event(Event::ListingCancelled {
+            ticket_id,
             event_id,
-            organizer,
-            metadata_hash,
-            event_start_time,
+            seller,
+            reason: ListingCancelReason::SellerCancelled,
         })
-        .expect("Failed to emit EventCreated");
+        .expect("Failed to emit ListingCancelled");
     }
     
-    /// Mintear tickets en venta primaria (batch minting)
-    /// Solo organizador del evento o admin
+    /// Cambiar el precio de un listado activo a precio fijo
+    /// Conserva la fecha original del listado
     #[export]
-    pub async fn mint_tickets(
-        &mut self,
-        event_id: U256,
-        buyer: ActorId,
-        amount: U256,
-        zones: Vec<Option<String>>, // Zonas opcionales para cada ticket
-    ) {
+    pub async fn update_listing_price(&mut self, ticket_id: U256, new_price: U256) {
         self.non_reentrant();
         
-        if buyer == ZERO_ID {
-            self.unlock();
-            panic(TicketError::InvalidInput);
-        }
+        let seller = msg::source();
         
-        if amount == U256::zero() {
+        if new_price == U256::zero() {
             self.unlock();
-            panic(TicketError::InvalidAmount);
+            panic(TicketError::InvalidPrice);
         }
         
+        let current_time = self.current_timestamp();
         let storage = self.get_mut();
         
-        let event_config = storage.events.get_mut(&event_id);
-        if event_config.is_none() {
+        // Obtener listado
+        let listing = storage.listings.get(&ticket_id);
+        if listing.is_none() {
             self.unlock();
-            panic(TicketError::EventNotFound);
+            panic(TicketError::ListingNotFound);
         }
-        let event_config = event_config.unwrap();
+        let listing = listing.unwrap().clone();
         
-        // Validar permisos
-        let caller = msg::source();
-        if caller != storage.admin && caller != event_config.organizer {
+        // Validar propiedad del listado
+        if listing.seller != seller {
             self.unlock();
-            panic(TicketError::Unauthorized);
+            panic(TicketError::TicketNotOwned);
         }
         
-        if !event_config.active {
+        // Solo los listados a precio fijo tienen un precio editable
+        if listing.kind != ListingKind::FixedPrice {
             self.unlock();
-            panic(TicketError::EventNotActive);
+            panic(TicketError::InvalidInput);
         }
         
-        // Validar disponibilidad
-        let available = event_config.tickets_total - event_config.tickets_minted;
-        if amount > available {
+        // Validar que el ticket siga siendo del vendedor y sin usar
+        let ticket = storage.tickets.get(&ticket_id);
+        if ticket.is_none() {
             self.unlock();
-            panic(TicketError::NotEnoughTickets);
+            panic(TicketError::TicketNotFound);
         }
+        let ticket = ticket.unwrap();
         
-        // Validar que las zonas coincidan con la cantidad
-        if !zones.is_empty() && U256::from(zones.len()) != amount {
+        if ticket.used {
             self.unlock();
-            panic(TicketError::InvalidInput);
+            panic(TicketError::TicketAlreadyUsed);
         }
         
-        // Crear tickets
-        let mut ticket_ids = Vec::new();
-        let mut zones_iter = zones.into_iter();
-        let mut zones_emitted = Vec::new();
+        if ticket.current_owner != seller {
+            self.unlock();
+            panic(TicketError::TicketNotOwned);
+        }
         
-        for _ in 0..amount.as_u64() {
-            storage.ticket_id_counter += U256::one();
-            let ticket_id = storage.ticket_id_counter;
-            
-            let zone = zones_iter.next().flatten();
-            let current_time = self.current_timestamp();
-
-
-            zones_emitted.push(zone.clone());
-            
-            let ticket = Ticket {
-                ticket_id,
-                event_id,
-                zone,
-                original_buyer: buyer,
-                current_owner: buyer,
-                used: false,
-                minted_at: current_time,
-            };
-            
-            storage.tickets.insert(ticket_id, ticket);
-            ticket_ids.push(ticket_id);
-            
-            // Agregar a la lista de tickets del evento
-            storage.event_tickets
-                .get_mut(&event_id)
-                .unwrap()
-                .push(ticket_id);
+        // Validar evento, precio máximo y ventana de reventa
+        let event_config = storage.events.get(&listing.event_id);
+        if event_config.is_none() {
+            self.unlock();
+            panic(TicketError::EventNotFound);
         }
+        let event_config = event_config.unwrap();
         
-        // Actualizar contador de tickets minteados
-        event_config.tickets_minted += amount;
+        if listing.is_expired(event_config, current_time) {
+            self.unlock();
+            panic(TicketError::ListingExpired);
+        }
         
-        // Mintear NFTs usando VMT
-        // Crear metadata para cada ticket
-        let mut token_ids = Vec::new();
-        let mut amounts = Vec::new();
-        let mut metadata_vec = Vec::new();
-        
-        for ticket_id in &ticket_ids {
-            token_ids.push(*ticket_id);
-            amounts.push(NFT_COUNT);
-            
-            // Crear metadata básica para el ticket
-            // En producción, esto puede incluir más información
-            metadata_vec.push(None::<extended_vmt_client::TokenMetadata>);
+        if let Err(e) = event_config.check_resale(new_price, ticket, current_time) {
+            self.unlock();
+            panic(e);
         }
         
-        // Llamar al contrato VMT para mintear los NFTs
-        let mint_request = vmt_io::MintBatch::encode_params_with_prefix("Vmt",buyer, token_ids, amounts, metadata_vec);
-        msg::send_bytes_for_reply(storage.vmt_contract_id, mint_request, 0, 5_000_000_000)
-            .expect("Error sending mint request to VMT contract")
-            .await
-            .expect("Error minting tickets from VMT contract");
+        // Reemplazar el listado manteniendo índices y estadísticas
+        let old_price = listing.price;
+        storage.remove_listing(&ticket_id);
+        storage.insert_listing(Listing {
+            price: new_price,
+            ..listing.clone()
+        });
         
-        self.emit_event(Event::TicketsMinted {
-            event_id,
-            ticket_ids: ticket_ids.clone(),
-            buyer,
-            amount,
-            zones: zones_emitted,
+        self.emit_event(Event::ListingPriceUpdated {
+            ticket_id,
+            event_id: listing.event_id,
+            seller,
+            old_price,
+            new_price,
         })
-        .expect("Failed to emit TicketsMinted");
+        .expect("Failed to emit ListingPriceUpdated");
+        
+        // Fan exchange: un listado que baja a precio de lista se asigna a la lista de espera
+        self.match_waitlist(ticket_id).await;
         
         self.unlock();
     }
     
-    /// Revender un ticket
-    /// Solo el propietario actual puede revender
+    /// Hacer una oferta por un ticket, listado o no
+    /// Pagable: el precio queda en escrow (VARA adjunto o `TransferFrom` del token del evento)
     #[export]
-    pub async fn resell_ticket(
-        &mut self,
-        ticket_id: U256,
-        buyer: ActorId,
-        price: U256,
-    ) {
+    pub async fn make_offer(&mut self, ticket_id: U256, price: U256, expires_at: u64) {
         self.non_reentrant();
         
-        if buyer == ZERO_ID {
+        let bidder = msg::source();
+        
+        if price == U256::zero() {
             self.unlock();
-            panic(TicketError::InvalidInput);
+            panic(TicketError::InvalidPrice);
         }
         
-        if price == U256::zero() {
+        let current_time = self.current_timestamp();
+        if expires_at <= current_time {
             self.unlock();
-            panic(TicketError::InvalidAmount);
+            panic(TicketError::InvalidInput);
         }
         
         let storage = self.get_mut();
         
-        let ticket = storage.tickets.get_mut(&ticket_id);
+        let ticket = storage.tickets.get(&ticket_id);
         if ticket.is_none() {
             self.unlock();
             panic(TicketError::TicketNotFound);
         }
         let ticket = ticket.unwrap();
         
-        // Validar que el ticket no esté usado
         if ticket.used {
             self.unlock();
             panic(TicketError::TicketAlreadyUsed);
         }
         
-        // Validar propiedad
-        if ticket.current_owner != msg::source() {
-            self.unlock();
-            panic(TicketError::TicketNotOwned);
-        }
-        
-        // Validar que no se transfiera a sí mismo
-        if buyer == ticket.current_owner {
+        if ticket.current_owner == bidder {
             self.unlock();
             panic(TicketError::InvalidInput);
         }
@@ -549,509 +3826,539 @@ impl TicketService {
         }
         let event_config = event_config.unwrap();
         
-        // Validar que la reventa esté habilitada
-        if !event_config.resale_config.enabled {
+        if let Err(e) = event_config.check_resale(price, ticket, current_time) {
             self.unlock();
-            panic(TicketError::ResaleDisabled);
-        }
-        
-        // Validar precio máximo
-        if let Some(max_price) = event_config.resale_config.max_price {
-            if price > max_price {
-                self.unlock();
-                panic(TicketError::PriceExceedsMaximum);
-            }
+            panic(e);
         }
         
-        // Validar ventana de tiempo
-        let current_time = self.current_timestamp();
-        if let Some(start_time) = event_config.resale_config.resale_start_time {
-            if current_time < start_time {
-                self.unlock();
-                panic(TicketError::ResaleWindowClosed);
+        // Depositar el precio en escrow
+        let currency = event_config.payment_token;
+        let event_id = ticket.event_id;
+        let payment = msg::value();
+        match currency {
+            None => {
+                if U256::from(payment) < price {
+                    self.unlock();
+                    panic(TicketError::InsufficientPayment);
+                }
             }
-        }
-        if let Some(end_time) = event_config.resale_config.resale_end_time {
-            if current_time > end_time {
-                self.unlock();
-                panic(TicketError::ResaleWindowClosed);
+            Some(token) => {
+                if !vft_transfer_from(token, bidder, exec::program_id(), price).await {
+                    storage.refund_value(bidder, payment).await;
+                    self.abort(bidder, TicketError::PaymentFailed);
+                    return;
+                }
             }
-        }
-        
-        // Calcular comisiones
-        let seller_share = (price.as_u128() * event_config.commission_config.seller_percentage as u128) 
-            / BASIS_POINTS as u128;
-        let organizer_share = (price.as_u128() * event_config.commission_config.organizer_percentage as u128) 
-            / BASIS_POINTS as u128;
-        let platform_share = (price.as_u128() * event_config.commission_config.platform_percentage as u128) 
-            / BASIS_POINTS as u128;
-        
-        // Validar que la suma sea correcta (puede haber diferencias por redondeo)
-        let total_distributed = seller_share + organizer_share + platform_share;
-        let price_u128 = price.as_u128();
-        if total_distributed > price_u128 {
-            self.unlock();
-            panic(TicketError::InvalidCommissionConfig);
-        }
-        
-        // Transferir el NFT del vendedor al comprador usando VMT
-        let transfer_request = vmt_io::TransferFrom::encode_params_with_prefix(
-            "Vmt",
-            ticket.current_owner,
-            buyer,
-            ticket_id,
-            NFT_COUNT,
-        );
-        msg::send_bytes_for_reply(storage.vmt_contract_id, transfer_request, 0, 5_000_000_000)
-            .expect("Error sending transfer request to VMT contract")
-            .await
-            .expect("Error transferring ticket NFT");
+        }
         
-        // Transferir pagos
-        // Nota: En Vara Network, el pago se maneja fuera del contrato o mediante un sistema de tokens
-        // El comprador debe enviar el pago antes de llamar a esta función, o usar un sistema de escrow
-        // Por ahora, asumimos que el pago se maneja externamente
+        storage.offer_id_counter += U256::one();
+        let offer_id = storage.offer_id_counter;
+        storage.insert_offer(Offer {
+            offer_id,
+            ticket_id,
+            event_id,
+            bidder,
+            price,
+            currency,
+            created_at: current_time,
+            expires_at,
+        });
         
-        // Actualizar propietario del ticket
-        let seller = ticket.current_owner;
-        let event_id = ticket.event_id;
-        ticket.current_owner = buyer;
+        // Devolver el excedente (todo el VARA adjunto si se pagó en token)
+        let refund = if currency.is_none() { payment - price.as_u128() } else { payment };
+        storage.refund_value(bidder, refund).await;
         
-        self.emit_event(Event::TicketResold {
+        self.emit_event(Event::OfferMade {
+            offer_id,
             ticket_id,
             event_id,
-            seller,
-            buyer,
+            bidder,
             price,
-            seller_share: U256::from(seller_share),
-            organizer_share: U256::from(organizer_share),
-            platform_share: U256::from(platform_share),
+            expires_at,
         })
-        .expect("Failed to emit TicketResold");
+        .expect("Failed to emit OfferMade");
         
         self.unlock();
     }
     
-    /// Marcar un ticket como usado
-    /// Solo escáner autorizado o admin
+    /// Aceptar una oferta sobre un ticket propio
+    /// Aplica las mismas validaciones de reventa que `list_ticket`
     #[export]
-    pub fn mark_ticket_used(&mut self, ticket_id: U256) {
-        self.require_scanner();
+    pub async fn accept_offer(&mut self, offer_id: U256) {
+        self.non_reentrant();
         
+        let seller = msg::source();
         let storage = self.get_mut();
         
-        let ticket = storage.tickets.get_mut(&ticket_id);
+        let offer = storage.offers.get(&offer_id);
+        if offer.is_none() {
+            self.unlock();
+            panic(TicketError::OfferNotFound);
+        }
+        let offer = offer.unwrap().clone();
+        
+        let current_time = self.current_timestamp();
+        if current_time > offer.expires_at {
+            self.unlock();
+            panic(TicketError::OfferExpired);
+        }
+        
+        let ticket = storage.tickets.get(&offer.ticket_id);
         if ticket.is_none() {
+            self.unlock();
             panic(TicketError::TicketNotFound);
         }
         let ticket = ticket.unwrap();
         
         if ticket.used {
+            self.unlock();
             panic(TicketError::TicketAlreadyUsed);
         }
         
-        ticket.used = true;
-        
-        self.emit_event(Event::TicketUsed {
-            ticket_id,
-            event_id: ticket.event_id,
-            scanner: msg::source(),
-        })
-        .expect("Failed to emit TicketUsed");
-    }
-    
-    /// Agregar organizador
-    /// Solo admin
-    #[export]
-    pub fn add_organizer(&mut self, organizer: ActorId) {
-        self.require_admin();
-        
-        if organizer == ZERO_ID {
-            panic(TicketError::InvalidInput);
+        if ticket.current_owner != seller {
+            self.unlock();
+            panic(TicketError::TicketNotOwned);
         }
         
-        let storage = self.get_mut();
-        storage.organizers.insert(organizer);
-        
-        self.emit_event(Event::OrganizerAdded { organizer })
-            .expect("Failed to emit OrganizerAdded");
-    }
-    
-    /// Remover organizador
-    /// Solo admin
-    #[export]
-    pub fn remove_organizer(&mut self, organizer: ActorId) {
-        self.require_admin();
+        let event_config = storage.events.get(&ticket.event_id);
+        if event_config.is_none() {
+            self.unlock();
+            panic(TicketError::EventNotFound);
+        }
+        let event_config = event_config.unwrap();
         
-        let storage = self.get_mut();
-        storage.organizers.remove(&organizer);
+        if let Err(e) = event_config.check_resale(offer.price, ticket, current_time) {
+            self.unlock();
+            panic(e);
+        }
         
-        self.emit_event(Event::OrganizerRemoved { organizer })
-            .expect("Failed to emit OrganizerRemoved");
-    }
-    
-    /// Agregar escáner
-    /// Solo admin
-    #[export]
-    pub fn add_scanner(&mut self, scanner: ActorId) {
-        self.require_admin();
+        // Validar cuotas por billetera
+        if let Err(e) = storage.check_purchase_limits(offer.bidder, &[(ticket.event_id, seller)]) {
+            self.unlock();
+            panic(e);
+        }
         
-        if scanner == ZERO_ID {
-            panic(TicketError::InvalidInput);
+        // Consumir la oferta y transferir el NFT al ofertante
+        // (si falla, la oferta vuelve a quedar vigente con su escrow)
+        let offer = storage.remove_offer(&offer_id).unwrap_or(offer);
+        storage.pending_tickets.insert(offer.ticket_id);
+        if !transfer_ticket_nft(storage.vmt_contract_id, seller, offer.bidder, offer.ticket_id).await {
+            storage.insert_offer(offer);
+            self.abort(seller, TicketError::TransferFailed);
+            return;
         }
         
-        let storage = self.get_mut();
-        storage.scanners.insert(scanner);
+        // Retirar un listado, paquete o subasta previos del ticket
+        let events = storage.delist_ticket(offer.ticket_id, ListingCancelReason::TicketTransferred);
+        self.emit_events(events);
         
-        self.emit_event(Event::ScannerAdded { scanner })
-            .expect("Failed to emit ScannerAdded");
-    }
-    
-    /// Remover escáner
-    /// Solo admin
-    #[export]
-    pub fn remove_scanner(&mut self, scanner: ActorId) {
-        self.require_admin();
+        // Liquidar con el escrow de la oferta
+        let split = storage.settle_resale(offer.ticket_id, offer.bidder, offer.price);
         
-        let storage = self.get_mut();
-        storage.scanners.remove(&scanner);
+        self.emit_event(Event::OfferAccepted {
+            offer_id,
+            ticket_id: offer.ticket_id,
+            event_id: offer.event_id,
+            seller,
+            buyer: offer.bidder,
+            price: offer.price,
+            seller_share: split.seller_share,
+            organizer_share: split.organizer_share,
+            platform_share: split.platform_share,
+            beneficiary_shares: split.beneficiary_shares,
+            rounding_remainder: split.rounding_remainder,
+        })
+        .expect("Failed to emit OfferAccepted");
         
-        self.emit_event(Event::ScannerRemoved { scanner })
-            .expect("Failed to emit ScannerRemoved");
+        self.unlock();
     }
     
-    /// Actualizar configuración de reventa de un evento
-    /// Solo organizador del evento o admin
+    /// Sumarse a la lista de espera del fan exchange de un evento (y zona)
+    /// Pagable: deposita en escrow el precio de lista de la zona
     #[export]
-    pub fn update_resale_config(
-        &mut self,
-        event_id: U256,
-        resale_config: ResaleConfig,
-    ) {
-        self.require_organizer();
-        
-        TicketService::validate_resale_config(&resale_config);
+    pub async fn join_waitlist(&mut self, event_id: U256, zone: Option<String>) {
+        self.non_reentrant();
         
+        let buyer = msg::source();
+        let current_time = self.current_timestamp();
         let storage = self.get_mut();
         
-        let event_config = storage.events.get_mut(&event_id);
+        let event_config = storage.events.get(&event_id);
         if event_config.is_none() {
+            self.unlock();
             panic(TicketError::EventNotFound);
         }
-        let event_config = event_config.unwrap();
+        let event_config = event_config.unwrap().clone();
         
-        // Validar permisos
-        let caller = msg::source();
-        if caller != storage.admin && caller != event_config.organizer {
-            panic(TicketError::Unauthorized);
+        if event_config.cancelled {
+            self.unlock();
+            panic(TicketError::EventCancelled);
         }
         
-        event_config.resale_config = resale_config;
+        if !event_config.fan_exchange {
+            self.unlock();
+            panic(TicketError::FanExchangeDisabled);
+        }
         
-        self.emit_event(Event::EventConfigUpdated { event_id })
-            .expect("Failed to emit EventConfigUpdated");
+        // Precio de lista de la zona
+        let face_value = event_config.primary_price_for(&zone);
+        if face_value.is_none() {
+            self.unlock();
+            panic(TicketError::PrimarySaleDisabled);
+        }
+        let face_value = face_value.unwrap();
+        
+        // Un lugar por comprador en cada fila
+        let key = (event_id, zone.clone());
+        if storage
+            .waitlists
+            .get(&key)
+            .is_some_and(|queue| queue.iter().any(|entry| entry.buyer == buyer))
+        {
+            self.unlock();
+            panic(TicketError::AlreadyInWaitlist);
+        }
+        
+        // Depositar el precio de lista en escrow
+        let currency = event_config.payment_token;
+        let payment = msg::value();
+        match currency {
+            None => {
+                if U256::from(payment) < face_value {
+                    self.unlock();
+                    panic(TicketError::InsufficientPayment);
+                }
+            }
+            Some(token) => {
+                if !vft_transfer_from(token, buyer, exec::program_id(), face_value).await {
+                    storage.refund_value(buyer, payment).await;
+                    self.abort(buyer, TicketError::PaymentFailed);
+                    return;
+                }
+            }
+        }
+        
+        storage.waitlists.entry(key).or_default().push(WaitlistEntry {
+            buyer,
+            deposit: face_value,
+            joined_at: current_time,
+        });
+        
+        // Devolver el excedente (todo el VARA adjunto si se pagó en token)
+        let refund = if currency.is_none() { payment - face_value.as_u128() } else { payment };
+        storage.refund_value(buyer, refund).await;
+        
+        self.emit_event(Event::WaitlistJoined {
+            event_id,
+            zone: zone.clone(),
+            buyer,
+            deposit: face_value,
+        })
+        .expect("Failed to emit WaitlistJoined");
+        
+        // Si ya hay un listado a precio de lista en la zona, asignarlo de inmediato
+        let pending = storage
+            .listings_in(storage.event_listings.get(&event_id))
+            .into_iter()
+            .filter(|listing| listing.is_public())
+            .filter(|listing| listing.kind == ListingKind::FixedPrice && listing.price == face_value)
+            .filter(|listing| !listing.is_expired(&event_config, current_time))
+            .filter(|listing| {
+                storage
+                    .tickets
+                    .get(&listing.ticket_id)
+                    .is_some_and(|ticket| ticket.zone == zone)
+            })
+            .min_by_key(|listing| (listing.listed_at, listing.ticket_id))
+            .map(|listing| listing.ticket_id);
+        if let Some(ticket_id) = pending {
+            self.match_waitlist(ticket_id).await;
+        }
+        
+        self.unlock();
     }
     
-    /// Actualizar configuración de comisiones de un evento
-    /// Solo organizador del evento o admin
+    /// Salir de la lista de espera y recuperar el depósito
     #[export]
-    pub fn update_commission_config(
-        &mut self,
-        event_id: U256,
-        commission_config: CommissionConfig,
-    ) {
-        self.require_organizer();
-        
-        TicketService::validate_commission_config(&commission_config);
+    pub async fn leave_waitlist(&mut self, event_id: U256, zone: Option<String>) {
+        self.non_reentrant();
         
+        let buyer = msg::source();
         let storage = self.get_mut();
         
-        let event_config = storage.events.get_mut(&event_id);
-        if event_config.is_none() {
-            panic(TicketError::EventNotFound);
+        let key = (event_id, zone.clone());
+        let position = storage
+            .waitlists
+            .get(&key)
+            .and_then(|queue| queue.iter().position(|entry| entry.buyer == buyer));
+        if position.is_none() {
+            self.unlock();
+            panic(TicketError::NotInWaitlist);
         }
-        let event_config = event_config.unwrap();
+        let entry = storage.remove_waitlist_entry(&key, position.unwrap());
         
-        // Validar permisos
-        let caller = msg::source();
-        if caller != storage.admin && caller != event_config.organizer {
-            panic(TicketError::Unauthorized);
-        }
+        let currency = storage.events.get(&event_id).and_then(|event_config| event_config.payment_token);
+        refund_escrow(storage, currency, buyer, entry.deposit).await;
         
-        event_config.commission_config = commission_config;
+        self.emit_event(Event::WaitlistLeft {
+            event_id,
+            zone,
+            buyer,
+        })
+        .expect("Failed to emit WaitlistLeft");
         
-        self.emit_event(Event::EventConfigUpdated { event_id })
-            .expect("Failed to emit EventConfigUpdated");
+        self.unlock();
     }
     
-    /// Desactivar/activar evento
-    /// Solo organizador del evento o admin
+    /// Liberar un ticket al fan exchange: se lista a precio de lista y se asigna
+    /// al primero de la lista de espera (o al próximo que se sume)
     #[export]
-    pub fn set_event_active(&mut self, event_id: U256, active: bool) {
-        self.require_organizer();
+    pub async fn release_ticket(&mut self, ticket_id: U256) {
+        self.non_reentrant();
         
+        let seller = msg::source();
+        let current_time = self.current_timestamp();
         let storage = self.get_mut();
         
-        let event_config = storage.events.get_mut(&event_id);
+        let ticket = storage.tickets.get(&ticket_id);
+        if ticket.is_none() {
+            self.unlock();
+            panic(TicketError::TicketNotFound);
+        }
+        let ticket = ticket.unwrap();
+        
+        let event_config = storage.events.get(&ticket.event_id);
         if event_config.is_none() {
+            self.unlock();
             panic(TicketError::EventNotFound);
         }
         let event_config = event_config.unwrap();
         
-        // Validar permisos
-        let caller = msg::source();
-        if caller != storage.admin && caller != event_config.organizer {
-            panic(TicketError::Unauthorized);
+        if !event_config.fan_exchange {
+            self.unlock();
+            panic(TicketError::FanExchangeDisabled);
         }
         
-        event_config.active = active;
+        let face_value = event_config.primary_price_for(&ticket.zone);
+        if face_value.is_none() {
+            self.unlock();
+            panic(TicketError::PrimarySaleDisabled);
+        }
+        let face_value = face_value.unwrap();
         
-        self.emit_event(Event::EventConfigUpdated { event_id })
-            .expect("Failed to emit EventConfigUpdated");
-    }
-    
-    /// Obtener información de un ticket
-    #[export]
-    pub fn get_ticket(&self, ticket_id: U256) -> Option<Ticket> {
-        self.get().tickets.get(&ticket_id).cloned()
-    }
-    
-    /// Obtener configuración de un evento
-    #[export]
-    pub fn get_event(&self, event_id: U256) -> Option<EventConfig> {
-        self.get().events.get(&event_id).cloned()
-    }
-    
-    /// Obtener tickets de un evento
-    #[export]
-    pub fn get_event_tickets(&self, event_id: U256) -> Vec<U256> {
-        self.get()
-            .event_tickets
-            .get(&event_id)
-            .cloned()
-            .unwrap_or_default()
-    }
-    
-    /// Obtener tickets de un usuario
-    #[export]
-    pub fn get_user_tickets(&self, user: ActorId) -> Vec<Ticket> {
-        self.get()
-            .tickets
-            .iter()
-            .filter(|(_, ticket)| ticket.current_owner == user)
-            .map(|(_, ticket)| ticket.clone())
-            .collect()
-    }
-    
-    /// Verificar si una dirección es organizador
-    #[export]
-    pub fn is_organizer(&self, address: ActorId) -> bool {
-        let storage = self.get();
-        storage.organizers.contains(&address) || address == storage.admin
-    }
-    
-    /// Verificar si una dirección es escáner
-    #[export]
-    pub fn is_scanner(&self, address: ActorId) -> bool {
-        let storage = self.get();
-        storage.scanners.contains(&address) || address == storage.admin
-    }
-    
-    /// Obtener estadísticas de un evento
-    #[export]
-    pub fn get_event_stats(&self, event_id: U256) -> Option<EventStats> {
-        let storage = self.get();
-        let event_config = storage.events.get(&event_id)?;
-        let tickets = storage.event_tickets.get(&event_id)?;
+        let event_id = match self.validate_listing(seller, ticket_id, face_value, current_time) {
+            Ok(event_id) => event_id,
+            Err(e) => {
+                self.unlock();
+                panic(e);
+            }
+        };
         
-        let used_tickets = tickets.iter()
-            .filter_map(|tid| storage.tickets.get(tid))
-            .filter(|t| t.used)
-            .count();
+        storage.insert_listing(Listing {
+            ticket_id,
+            seller,
+            price: face_value,
+            listed_at: current_time,
+            event_id,
+            kind: ListingKind::FixedPrice,
+            expires_at: None,
+            reserved_for: None,
+        });
         
-        Some(EventStats {
+        self.emit_event(Event::TicketListed {
+            ticket_id,
             event_id,
-            tickets_total: event_config.tickets_total,
-            tickets_minted: event_config.tickets_minted,
-            tickets_used: used_tickets as u64,
-            active: event_config.active,
+            seller,
+            price: face_value,
+            reserved_for: None,
         })
+        .expect("Failed to emit TicketListed");
+        
+        self.match_waitlist(ticket_id).await;
+        
+        self.unlock();
     }
     
-    /// Obtener estado completo del contrato
+    /// Cancelar una oferta propia y recuperar el escrow
     #[export]
-    pub fn get_storage(&self) -> State {
-        self.get().clone().into()
-    }
-}
-
-/// Servicio de Marketplace (separado del servicio de Tickets)
-struct MarketService(());
-
-impl MarketService {
-    pub fn new() -> Self {
-        Self(())
-    }
-    
-    pub fn get_mut(&mut self) -> &'static mut Storage {
-        unsafe { STORAGE.as_mut().expect("Storage is not initialized") }
+    pub async fn cancel_offer(&mut self, offer_id: U256) {
+        self.non_reentrant();
+        
+        let bidder = msg::source();
+        let storage = self.get_mut();
+        
+        let offer = storage.offers.get(&offer_id);
+        if offer.is_none() {
+            self.unlock();
+            panic(TicketError::OfferNotFound);
+        }
+        let offer = offer.unwrap();
+        
+        if offer.bidder != bidder {
+            self.unlock();
+            panic(TicketError::Unauthorized);
+        }
+        
+        let offer = storage.remove_offer(&offer_id).unwrap();
+        refund_escrow(storage, offer.currency, bidder, offer.price).await;
+        
+        self.emit_event(Event::OfferCancelled {
+            offer_id,
+            ticket_id: offer.ticket_id,
+            event_id: offer.event_id,
+            bidder,
+        })
+        .expect("Failed to emit OfferCancelled");
+        
+        self.unlock();
     }
     
-    pub fn get(&self) -> &'static Storage {
-        unsafe { STORAGE.as_ref().expect("Storage is not initialized") }
+    /// Obtener una oferta
+    #[export]
+    pub fn get_offer(&self, offer_id: U256) -> Option<Offer> {
+        self.get().offers.get(&offer_id).cloned()
     }
     
-    /// Guard contra reentrancy
-    fn non_reentrant(&mut self) {
-        let storage = self.get_mut();
-        if storage.locked {
-            panic(TicketError::ReentrancyDetected);
-        }
-        storage.locked = true;
+    /// Obtener las ofertas sobre un ticket
+    #[export]
+    pub fn get_ticket_offers(&self, ticket_id: U256) -> Vec<Offer> {
+        let storage = self.get();
+        storage
+            .ticket_offers
+            .get(&ticket_id)
+            .map(|ids| ids.iter().filter_map(|id| storage.offers.get(id)).cloned().collect())
+            .unwrap_or_default()
     }
     
-    fn unlock(&mut self) {
-        let storage = self.get_mut();
-        storage.locked = false;
+    /// Obtener las ofertas de un ofertante
+    #[export]
+    pub fn get_bidder_offers(&self, bidder: ActorId) -> Vec<Offer> {
+        let storage = self.get();
+        storage
+            .bidder_offers
+            .get(&bidder)
+            .map(|ids| ids.iter().filter_map(|id| storage.offers.get(id)).cloned().collect())
+            .unwrap_or_default()
     }
     
-    /// Obtiene timestamp actual
-fn current_timestamp(&self) -> u64 {
-    exec::block_timestamp()
-}
-}
-
-#[service(events = Event)]
-impl MarketService {
-    /// Listar un ticket para reventa en el Marketplace
+    /// Crear una subasta inglesa para un ticket propio
     #[export]
-    pub async fn list_ticket(&mut self,seller: ActorId, ticket_id: U256, price: U256) {
-        self.non_reentrant();
+    pub fn create_auction(
+        &mut self,
+        ticket_id: U256,
+        reserve_price: U256,
+        min_increment: U256,
+        end_time: u64,
+    ) {
+        let seller = msg::source();
         
-        if price == U256::zero() {
-            self.unlock();
+        if reserve_price == U256::zero() || min_increment == U256::zero() {
             panic(TicketError::InvalidPrice);
         }
         
-        if seller == ZERO_ID {
-            self.unlock();
+        let current_time = self.current_timestamp();
+        if end_time <= current_time {
             panic(TicketError::InvalidInput);
         }
         
         let storage = self.get_mut();
         
-        // Validar que no esté ya listado
-        if storage.listings.contains_key(&ticket_id) {
-            self.unlock();
+        // Validar que ninguna operación en curso pueda restaurar la venta del ticket
+        if let Err(e) = storage.check_not_pending(&ticket_id) {
+            panic(e);
+        }
+        
+        // Validar que no esté ya listado, en subasta ni en un paquete
+        if storage.is_on_sale(&ticket_id) {
             panic(TicketError::TicketAlreadyListed);
         }
         
-        // Validar que el ticket existe
         let ticket = storage.tickets.get(&ticket_id);
         if ticket.is_none() {
-            self.unlock();
             panic(TicketError::TicketNotFound);
         }
         let ticket = ticket.unwrap();
         
-        // Validar que no esté usado
         if ticket.used {
-            self.unlock();
             panic(TicketError::TicketAlreadyUsed);
         }
         
-        // Validar propiedad
         if ticket.current_owner != seller {
-            self.unlock();
             panic(TicketError::TicketNotOwned);
         }
         
-        // Validar evento
         let event_config = storage.events.get(&ticket.event_id);
         if event_config.is_none() {
-            self.unlock();
             panic(TicketError::EventNotFound);
         }
         let event_config = event_config.unwrap();
         
-        // Validar que la reventa esté habilitada
-        if !event_config.resale_config.enabled {
-            self.unlock();
-            panic(TicketError::ResaleDisabled);
-        }
-        
-        // Validar precio máximo
-        if let Some(max_price) = event_config.resale_config.max_price {
-            if price > max_price {
-                self.unlock();
-                panic(TicketError::PriceExceedsMaximum);
-            }
+        if let Err(e) = event_config.check_resale(reserve_price, ticket, current_time) {
+            panic(e);
         }
         
-        // Validar ventana de tiempo
-        let current_time = self.current_timestamp();
-        if let Some(start_time) = event_config.resale_config.resale_start_time {
-            if current_time < start_time {
-                self.unlock();
-                panic(TicketError::ResaleWindowClosed);
-            }
-        }
-        if let Some(end_time) = event_config.resale_config.resale_end_time {
-            if current_time > end_time {
-                self.unlock();
-                panic(TicketError::ResaleWindowClosed);
-            }
+        // La subasta debe cerrar dentro de la ventana de reventa y antes del inicio del evento
+        if end_time > event_config.resale_deadline() {
+            panic(TicketError::ResaleWindowClosed);
         }
         
-        // Crear listado
-        let listing = Listing {
+        let event_id = ticket.event_id;
+        storage.auctions.insert(
             ticket_id,
-            seller,
-            price,
-            listed_at: current_time,
-            event_id: ticket.event_id,
-        };
-        
-        storage.listings.insert(ticket_id, listing.clone());
+            Auction {
+                ticket_id,
+                event_id,
+                seller,
+                reserve_price,
+                min_increment,
+                started_at: current_time,
+                end_time,
+                highest_bidder: None,
+                highest_bid: U256::zero(),
+                currency: event_config.payment_token,
+            },
+        );
         
-        self.emit_event(Event::TicketListed {
+        self.emit_event(Event::AuctionCreated {
             ticket_id,
-            event_id: ticket.event_id,
+            event_id,
             seller,
-            price,
+            reserve_price,
+            min_increment,
+            end_time,
         })
-        .expect("Failed to emit TicketListed");
-        
-        self.unlock();
+        .expect("Failed to emit AuctionCreated");
     }
     
-    /// Comprar un ticket listado en el Marketplace
+    /// Pujar en una subasta
+    /// Pagable: la puja queda en escrow; la puja superada se acredita al saldo reclamable
+    /// de su ofertante. Una puja cerca del cierre extiende la subasta.
     #[export]
-    pub async fn buy_ticket(&mut self, buyer: ActorId, ticket_id: U256) {
+    pub async fn place_bid(&mut self, ticket_id: U256, amount: U256) {
         self.non_reentrant();
         
-        if buyer == ZERO_ID {
+        let bidder = msg::source();
+        let current_time = self.current_timestamp();
+        let storage = self.get_mut();
+        
+        let auction = storage.auctions.get(&ticket_id);
+        if auction.is_none() {
             self.unlock();
-            panic(TicketError::InvalidInput);
+            panic(TicketError::AuctionNotFound);
         }
+        let auction = auction.unwrap().clone();
         
-        let storage = self.get_mut();
-        
-        // Obtener listado
-        let listing = storage.listings.get(&ticket_id);
-        if listing.is_none() {
+        if current_time > auction.end_time {
             self.unlock();
-            panic(TicketError::ListingNotFound);
+            panic(TicketError::AuctionEnded);
         }
-        let listing = listing.unwrap().clone();
         
-        // Validar que el comprador no sea el vendedor
-        if buyer == listing.seller {
+        if bidder == auction.seller {
             self.unlock();
             panic(TicketError::InvalidInput);
         }
         
-        // Validar que el ticket todavía es válido
+        // Validar que el ticket siga siendo vendible por el subastador
         let ticket = storage.tickets.get(&ticket_id);
         if ticket.is_none() {
             self.unlock();
@@ -1059,124 +4366,311 @@ impl MarketService {
         }
         let ticket = ticket.unwrap();
         
-        // Validar que no esté usado
         if ticket.used {
             self.unlock();
             panic(TicketError::TicketAlreadyUsed);
         }
         
-        // Validar propiedad
-        if ticket.current_owner != listing.seller {
+        if ticket.current_owner != auction.seller {
             self.unlock();
             panic(TicketError::TicketNotOwned);
         }
         
-        // Validar evento
-        let event_config = storage.events.get(&ticket.event_id);
+        // Validar la puja mínima
+        let min_bid = if auction.highest_bidder.is_some() {
+            auction.highest_bid + auction.min_increment
+        } else {
+            auction.reserve_price
+        };
+        if amount < min_bid {
+            self.unlock();
+            panic(TicketError::BidTooLow);
+        }
+        
+        // Validar el evento y el precio máximo de reventa
+        let event_config = storage.events.get(&auction.event_id);
         if event_config.is_none() {
             self.unlock();
             panic(TicketError::EventNotFound);
         }
         let event_config = event_config.unwrap();
         
-        // Validar precio del listado vs precio máximo actual
-        if let Some(max_price) = event_config.resale_config.max_price {
-            if listing.price > max_price {
-                self.unlock();
-                panic(TicketError::PriceExceedsMaximum);
+        if event_config.cancelled {
+            self.unlock();
+            panic(TicketError::EventCancelled);
+        }
+        
+        if !event_config.active {
+            self.unlock();
+            panic(TicketError::EventNotActive);
+        }
+        
+        if let Err(e) = event_config.check_resale_price(amount, ticket) {
+            self.unlock();
+            panic(e);
+        }
+        
+        // Validar las cuotas por billetera (tenencia del ofertante y reventas del subastador)
+        if let Err(e) = storage.check_purchase_limits(bidder, &[(auction.event_id, auction.seller)]) {
+            self.unlock();
+            panic(e);
+        }
+        
+        // Anti-sniping: extender la subasta si la puja llega cerca del cierre,
+        // sin pasar el fin de la ventana de reventa ni el inicio del evento
+        let mut end_time = auction.end_time;
+        if end_time - current_time < AUCTION_EXTENSION_WINDOW {
+            end_time = (current_time + AUCTION_EXTENSION_WINDOW)
+                .min(event_config.resale_deadline())
+                .max(auction.end_time);
+        }
+        
+        // Depositar la puja en escrow
+        let currency = auction.currency;
+        let payment = msg::value();
+        match currency {
+            None => {
+                if U256::from(payment) < amount {
+                    self.unlock();
+                    panic(TicketError::InsufficientPayment);
+                }
+            }
+            Some(token) => {
+                if !vft_transfer_from(token, bidder, exec::program_id(), amount).await {
+                    storage.refund_value(bidder, payment).await;
+                    self.abort(bidder, TicketError::PaymentFailed);
+                    return;
+                }
             }
         }
         
-        // Guardar datos antes de remover el listado
-        let seller = listing.seller;
-        let price = listing.price;
-        let event_id = ticket.event_id;
+        // La subasta pudo retirarse mientras se esperaba el pago: devolver la puja
+        let Some(auction_mut) = storage.auctions.get_mut(&ticket_id) else {
+            storage.return_payment(bidder, currency, amount, payment).await;
+            self.abort(bidder, TicketError::AuctionNotFound);
+            return;
+        };
+        let previous_bidder = auction_mut.highest_bidder.replace(bidder);
+        let previous_bid = core::mem::replace(&mut auction_mut.highest_bid, amount);
+        auction_mut.end_time = end_time;
+        
+        // Devolver la puja superada al saldo reclamable de su ofertante
+        if let Some(previous_bidder) = previous_bidder {
+            storage.credit(previous_bidder, currency, previous_bid);
+        }
+        
+        // Devolver el excedente (todo el VARA adjunto si se pagó en token)
+        let refund = if currency.is_none() { payment - amount.as_u128() } else { payment };
+        storage.refund_value(bidder, refund).await;
+        
+        self.emit_event(Event::BidPlaced {
+            ticket_id,
+            bidder,
+            amount,
+            end_time,
+        })
+        .expect("Failed to emit BidPlaced");
+        
+        self.unlock();
+    }
+    
+    /// Liquidar una subasta finalizada
+    /// Cualquiera puede llamarla una vez pasado el cierre
+    #[export]
+    pub async fn settle_auction(&mut self, ticket_id: U256) {
+        self.non_reentrant();
         
-        // Remover listado antes de procesar (previene doble compra)
-        storage.listings.remove(&ticket_id);
+        let current_time = self.current_timestamp();
+        let storage = self.get_mut();
         
-        // Calcular comisiones
-        let seller_share = (price.as_u128() * event_config.commission_config.seller_percentage as u128) 
-            / BASIS_POINTS as u128;
-        let organizer_share = (price.as_u128() * event_config.commission_config.organizer_percentage as u128) 
-            / BASIS_POINTS as u128;
-        let platform_share = (price.as_u128() * event_config.commission_config.platform_percentage as u128) 
-            / BASIS_POINTS as u128;
+        let auction = storage.auctions.get(&ticket_id);
+        if auction.is_none() {
+            self.unlock();
+            panic(TicketError::AuctionNotFound);
+        }
+        let auction = auction.unwrap().clone();
         
-        // Validar que la suma sea correcta
-        let total_distributed = seller_share + organizer_share + platform_share;
-        let price_u128 = price.as_u128();
-        if total_distributed > price_u128 {
+        if current_time <= auction.end_time {
             self.unlock();
-            panic(TicketError::InvalidCommissionConfig);
+            panic(TicketError::AuctionNotEnded);
         }
         
-        // Transferir el NFT del vendedor al comprador usando VMT
-        let transfer_request = vmt_io::TransferFrom::encode_params_with_prefix(
-            "Vmt",
-            seller,
-            buyer,
-            ticket_id,
-            NFT_COUNT,
-        );
-        msg::send_bytes_for_reply(storage.vmt_contract_id, transfer_request, 0, 5_000_000_000)
-            .expect("Error sending transfer request to VMT contract")
-            .await
-            .expect("Error transferring ticket NFT");
+        storage.auctions.remove(&ticket_id);
+        
+        // Sin pujas: la subasta termina sin venta
+        let Some(winner) = auction.highest_bidder else {
+            self.emit_event(Event::AuctionCancelled {
+                ticket_id,
+                event_id: auction.event_id,
+                seller: auction.seller,
+            })
+            .expect("Failed to emit AuctionCancelled");
+            self.unlock();
+            return;
+        };
         
-        // Actualizar propietario del ticket
-        let ticket_mut = storage.tickets.get_mut(&ticket_id).unwrap();
-        ticket_mut.current_owner = buyer;
+        // Si el ticket ya no puede venderse, devolver la puja ganadora. La reventa se
+        // revalida al cierre de la subasta (ventana, límites de precio, evento activo)
+        // junto con las cuotas por billetera del ganador y del subastador
+        let still_valid = storage
+            .tickets
+            .get(&ticket_id)
+            .filter(|ticket| !ticket.used && ticket.current_owner == auction.seller)
+            .zip(storage.events.get(&auction.event_id))
+            .is_some_and(|(ticket, event_config)| {
+                event_config.active
+                    && auction.end_time <= event_config.resale_deadline()
+                    && event_config
+                        .check_resale(auction.highest_bid, ticket, auction.end_time)
+                        .is_ok()
+            })
+            && storage
+                .check_purchase_limits(winner, &[(auction.event_id, auction.seller)])
+                .is_ok();
+        if !still_valid {
+            storage.credit(winner, auction.currency, auction.highest_bid);
+            self.emit_event(Event::AuctionCancelled {
+                ticket_id,
+                event_id: auction.event_id,
+                seller: auction.seller,
+            })
+            .expect("Failed to emit AuctionCancelled");
+            self.unlock();
+            return;
+        }
         
-        // Transferir pagos
-        // Nota: En Vara Network, el pago se maneja fuera del contrato o mediante un sistema de tokens
-        // El comprador debe enviar el pago antes de llamar a esta función, o usar un sistema de escrow
+        // Transferir el NFT al ganador y liquidar con la puja en escrow
+        // (si falla, la subasta se restaura con su puja para reintentar la liquidación)
+        storage.pending_tickets.insert(ticket_id);
+        if !transfer_ticket_nft(storage.vmt_contract_id, auction.seller, winner, ticket_id).await {
+            let event_id = auction.event_id;
+            storage.auctions.insert(ticket_id, auction);
+            let events = storage.delist_if_closed(ticket_id, event_id);
+            self.emit_events(events);
+            self.abort(msg::source(), TicketError::TransferFailed);
+            return;
+        }
+        let split = storage.settle_resale(ticket_id, winner, auction.highest_bid);
         
-        self.emit_event(Event::TicketSold {
+        self.emit_event(Event::AuctionSettled {
             ticket_id,
-            event_id,
-            seller,
-            buyer,
-            price,
-            seller_share: U256::from(seller_share),
-            organizer_share: U256::from(organizer_share),
-            platform_share: U256::from(platform_share),
+            event_id: auction.event_id,
+            seller: auction.seller,
+            winner,
+            price: auction.highest_bid,
+            seller_share: split.seller_share,
+            organizer_share: split.organizer_share,
+            platform_share: split.platform_share,
+            beneficiary_shares: split.beneficiary_shares,
+            rounding_remainder: split.rounding_remainder,
         })
-        .expect("Failed to emit TicketSold");
+        .expect("Failed to emit AuctionSettled");
         
         self.unlock();
     }
     
-    /// Cancelar un listado activo
+    /// Cancelar una subasta propia que todavía no recibió pujas
     #[export]
-    pub fn cancel_listing(&mut self, seller: ActorId, ticket_id: U256) {
+    pub fn cancel_auction(&mut self, ticket_id: U256) {
+        let seller = msg::source();
         let storage = self.get_mut();
         
-        // Obtener listado
-        let listing = storage.listings.get(&ticket_id);
-        if listing.is_none() {
-            panic(TicketError::ListingNotFound);
+        let auction = storage.auctions.get(&ticket_id);
+        if auction.is_none() {
+            panic(TicketError::AuctionNotFound);
         }
-        let listing = listing.unwrap();
+        let auction = auction.unwrap();
         
-        // Validar propiedad del listado
-        if listing.seller != seller {
+        if auction.seller != seller {
             panic(TicketError::TicketNotOwned);
         }
         
-        // Remover listado
-        let event_id = listing.event_id;
-        storage.listings.remove(&ticket_id);
+        if auction.highest_bidder.is_some() {
+            panic(TicketError::AuctionHasBids);
+        }
+        
+        let event_id = auction.event_id;
+        storage.auctions.remove(&ticket_id);
+        
+        self.emit_event(Event::AuctionCancelled {
+            ticket_id,
+            event_id,
+            seller,
+        })
+        .expect("Failed to emit AuctionCancelled");
+    }
+    
+    /// Obtener una subasta
+    #[export]
+    pub fn get_auction(&self, ticket_id: U256) -> Option<Auction> {
+        self.get().auctions.get(&ticket_id).cloned()
+    }
+    
+    /// Obtener todas las subastas activas
+    #[export]
+    pub fn get_all_auctions(&self) -> Vec<Auction> {
+        self.get().auctions.values().cloned().collect()
+    }
+    
+    /// Remover hasta `limit` listados y paquetes vencidos
+    /// Cualquiera puede llamarla; devuelve la cantidad de listados y paquetes removidos
+    #[export]
+    pub fn sweep_expired_listings(&mut self, limit: u32) -> u32 {
+        let current_time = self.current_timestamp();
+        let storage = self.get_mut();
+        
+        let expired: Vec<U256> = storage
+            .listings
+            .values()
+            .filter(|listing| match storage.events.get(&listing.event_id) {
+                Some(event_config) => listing.is_expired(event_config, current_time),
+                None => true,
+            })
+            .take(limit as usize)
+            .map(|listing| listing.ticket_id)
+            .collect();
+        
+        for ticket_id in &expired {
+            if let Some(listing) = storage.remove_listing(ticket_id) {
+                self.emit_event(Event::ListingExpired {
+                    ticket_id: listing.ticket_id,
+                    event_id: listing.event_id,
+                    seller: listing.seller,
+                })
+                .expect("Failed to emit ListingExpired");
+            }
+        }
+        
+        // Con el límite restante, remover paquetes vencidos
+        let expired_bundles: Vec<U256> = storage
+            .bundles
+            .values()
+            .filter(|bundle| match storage.events.get(&bundle.event_id) {
+                Some(event_config) => bundle.is_expired(event_config, current_time),
+                None => true,
+            })
+            .take(limit as usize - expired.len())
+            .map(|bundle| bundle.bundle_id)
+            .collect();
+        
+        for bundle_id in &expired_bundles {
+            if let Some(bundle) = storage.remove_bundle(bundle_id) {
+                self.emit_event(Event::BundleCancelled {
+                    bundle_id: bundle.bundle_id,
+                    event_id: bundle.event_id,
+                    seller: bundle.seller,
+                    reason: ListingCancelReason::Expired,
+                })
+                .expect("Failed to emit BundleCancelled");
+            }
+        }
         
-        self.emit_event(Event::ListingCancelled {
-            ticket_id,
-            event_id,
-            seller,
-        })
-        .expect("Failed to emit ListingCancelled");
+        (expired.len() + expired_bundles.len()) as u32
     }
     
     /// Obtener información de un listado
+    /// Devuelve también los reservados (con `reserved_for` visible): la reserva
+    /// se hace cumplir al comprar, no al consultar
     #[export]
     pub fn get_listing(&self, ticket_id: U256) -> Option<Listing> {
         self.get().listings.get(&ticket_id).cloned()
@@ -1185,19 +4679,207 @@ impl MarketService {
     /// Obtener todos los listados activos
     #[export]
     pub fn get_all_listings(&self) -> Vec<Listing> {
-        self.get().listings.values().cloned().collect()
+        self.get()
+            .listings
+            .values()
+            .filter(|listing| listing.is_public())
+            .cloned()
+            .collect()
+    }
+    
+    /// Consultar listados vigentes con filtros, orden y paginación
+    /// `cursor` es la posición del primer resultado (usar `next_cursor` de la página anterior)
+    #[export]
+    pub fn query_listings(
+        &self,
+        filter: ListingFilter,
+        sort: ListingSort,
+        cursor: u32,
+        limit: u32,
+    ) -> ListingPage {
+        let storage = self.get();
+        let current_time = self.current_timestamp();
+        
+        // Partir del índice más selectivo disponible (el menor si se filtra por ambos);
+        // solo una consulta sin vendedor ni evento recorre todos los listados
+        let by_seller = filter.seller.map(|seller| storage.seller_listings.get(&seller));
+        let by_event = filter.event_id.map(|event_id| storage.event_listings.get(&event_id));
+        let candidates: Vec<&Listing> = match (by_seller, by_event) {
+            (Some(seller_ids), Some(event_ids)) => {
+                let size = |ids: Option<&HashSet<U256>>| ids.map_or(0, |ids| ids.len());
+                if size(seller_ids) <= size(event_ids) {
+                    storage.listings_in(seller_ids)
+                } else {
+                    storage.listings_in(event_ids)
+                }
+            }
+            (Some(ids), None) | (None, Some(ids)) => storage.listings_in(ids),
+            (None, None) => storage.listings.values().collect(),
+        };
+        
+        // Filtrar listados vigentes con su precio actual
+        let mut matches: Vec<(&Listing, U256)> = candidates
+            .into_iter()
+            .filter(|listing| listing.is_public())
+            .filter(|listing| filter.event_id.is_none_or(|event_id| listing.event_id == event_id))
+            .filter(|listing| filter.seller.is_none_or(|seller| listing.seller == seller))
+            .filter(|listing| {
+                filter.zone.is_none()
+                    || storage
+                        .tickets
+                        .get(&listing.ticket_id)
+                        .is_some_and(|ticket| ticket.zone == filter.zone)
+            })
+            .filter(|listing| {
+                storage
+                    .events
+                    .get(&listing.event_id)
+                    .is_some_and(|event_config| !listing.is_expired(event_config, current_time))
+            })
+            .map(|listing| (listing, listing.current_price(current_time)))
+            .filter(|(_, price)| filter.min_price.is_none_or(|min| *price >= min))
+            .filter(|(_, price)| filter.max_price.is_none_or(|max| *price <= max))
+            .collect();
+        
+        // Orden (desempate por ticket_id para que las páginas sean estables)
+        let compare = |(a, a_price): &(&Listing, U256), (b, b_price): &(&Listing, U256)| match sort {
+            ListingSort::PriceAsc => (a_price, a.ticket_id).cmp(&(b_price, b.ticket_id)),
+            ListingSort::PriceDesc => (b_price, b.ticket_id).cmp(&(a_price, a.ticket_id)),
+            ListingSort::ListedAtAsc => (a.listed_at, a.ticket_id).cmp(&(b.listed_at, b.ticket_id)),
+            ListingSort::ListedAtDesc => (b.listed_at, b.ticket_id).cmp(&(a.listed_at, a.ticket_id)),
+        };
+        
+        let total = matches.len() as u32;
+        let limit = limit.min(MAX_QUERY_LIMIT);
+        let start = cursor.min(total) as usize;
+        let end = cursor.saturating_add(limit).min(total) as usize;
+        
+        // Ordenar solo hasta el final de la página pedida
+        if end < matches.len() {
+            if end > 0 {
+                matches.select_nth_unstable_by(end - 1, compare);
+            }
+            matches.truncate(end);
+        }
+        matches.sort_unstable_by(compare);
+        let listings: Vec<Listing> = matches[start..end]
+            .iter()
+            .map(|(listing, _)| (*listing).clone())
+            .collect();
+        
+        // Una página vacía (por ejemplo con `limit` 0) no tiene continuación
+        let next_cursor = if end > start && (end as u32) < total { Some(end as u32) } else { None };
+        
+        ListingPage {
+            listings,
+            next_cursor,
+            total,
+        }
     }
     
     /// Obtener listados de un vendedor
     #[export]
     pub fn get_seller_listings(&self, seller: ActorId) -> Vec<Listing> {
+        let storage = self.get();
+        storage
+            .listings_in(storage.seller_listings.get(&seller))
+            .into_iter()
+            .filter(|listing| listing.is_public())
+            .cloned()
+            .collect()
+    }
+    
+    /// Obtener la lista de espera del fan exchange de un evento (y zona), en orden
+    #[export]
+    pub fn get_waitlist(&self, event_id: U256, zone: Option<String>) -> Vec<WaitlistEntry> {
+        self.get().waitlists.get(&(event_id, zone)).cloned().unwrap_or_default()
+    }
+    
+    /// Obtener listados activos de un evento
+    #[export]
+    pub fn get_event_listings(&self, event_id: U256) -> Vec<Listing> {
+        let storage = self.get();
+        storage
+            .listings_in(storage.event_listings.get(&event_id))
+            .into_iter()
+            .filter(|listing| listing.is_public())
+            .cloned()
+            .collect()
+    }
+    
+    /// Obtener un paquete
+    #[export]
+    pub fn get_bundle(&self, bundle_id: U256) -> Option<Bundle> {
+        self.get().bundles.get(&bundle_id).cloned()
+    }
+    
+    /// Obtener los paquetes activos de un evento
+    #[export]
+    pub fn get_event_bundles(&self, event_id: U256) -> Vec<Bundle> {
+        self.get()
+            .bundles
+            .values()
+            .filter(|bundle| bundle.event_id == event_id)
+            .cloned()
+            .collect()
+    }
+    
+    /// Obtener los listados reservados para un comprador
+    #[export]
+    pub fn get_reserved_listings(&self, buyer: ActorId) -> Vec<Listing> {
         self.get()
             .listings
             .values()
-            .filter(|listing| listing.seller == seller)
+            .filter(|listing| listing.reserved_for == Some(buyer))
             .cloned()
             .collect()
     }
+    
+    /// Retirar el saldo reclamable del caller en una moneda (None = VARA nativo)
+    #[export]
+    pub async fn withdraw(&mut self, currency: Option<ActorId>) {
+        let account = msg::source();
+        let storage = self.get_mut();
+        
+        // Descontar el saldo antes de enviar (previene doble retiro sin bloquear otras operaciones)
+        let amount = storage.balances.remove(&(account, currency)).unwrap_or_default();
+        if amount.is_zero() {
+            panic(TicketError::NothingToWithdraw);
+        }
+        
+        let sent = match currency {
+            None => send_value(account, amount.as_u128()).await,
+            Some(token) => vft_transfer(token, account, amount).await,
+        };
+        
+        // Si el envío es rechazado, el saldo se restaura (sumado a lo acreditado
+        // mientras se esperaba) y se informa el fallo
+        if !sent {
+            storage.credit(account, currency, amount);
+            let _ = self.emit_event(Event::OperationFailed {
+                account,
+                error: TicketError::PaymentFailed,
+            });
+            return;
+        }
+        
+        self.emit_event(Event::Withdrawn {
+            account,
+            currency,
+            amount,
+        })
+        .expect("Failed to emit Withdrawn");
+    }
+    
+    /// Obtener el saldo reclamable de una cuenta en una moneda
+    #[export]
+    pub fn get_claimable_balance(&self, account: ActorId, currency: Option<ActorId>) -> U256 {
+        self.get()
+            .balances
+            .get(&(account, currency))
+            .copied()
+            .unwrap_or_default()
+    }
 }
 
 pub struct TicketProgram(());
@@ -1223,6 +4905,167 @@ pub fn panic(err: impl Debug) -> ! {
     ext::panic(format!("{err:?}"))
 }
 
+/// Mintea en VMT un NFT por cada ticket a nombre de `buyer` y devuelve si tuvo éxito
+async fn mint_ticket_nfts(vmt_contract_id: ActorId, buyer: ActorId, ticket_ids: &[U256]) -> bool {
+    // Crear metadata para cada ticket
+    let mut token_ids = Vec::new();
+    let mut amounts = Vec::new();
+    let mut metadata_vec = Vec::new();
+    
+    for ticket_id in ticket_ids {
+        token_ids.push(*ticket_id);
+        amounts.push(NFT_COUNT);
+        
+        // Crear metadata básica para el ticket
+        // En producción, esto puede incluir más información
+        metadata_vec.push(None::<extended_vmt_client::TokenMetadata>);
+    }
+    
+    // Llamar al contrato VMT para mintear los NFTs
+    let mint_request = vmt_io::MintBatch::encode_params_with_prefix("Vmt", buyer, token_ids, amounts, metadata_vec);
+    match msg::send_bytes_for_reply(vmt_contract_id, mint_request, 0, 5_000_000_000) {
+        Ok(reply) => reply.await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Transfiere en VMT el NFT de un ticket entre dos cuentas y devuelve si tuvo éxito
+async fn transfer_ticket_nft(vmt_contract_id: ActorId, from: ActorId, to: ActorId, ticket_id: U256) -> bool {
+    let transfer_request = vmt_io::TransferFrom::encode_params_with_prefix(
+        "Vmt",
+        from,
+        to,
+        ticket_id,
+        NFT_COUNT,
+    );
+    match msg::send_bytes_for_reply(vmt_contract_id, transfer_request, 0, 5_000_000_000) {
+        Ok(reply) => reply.await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Transfiere en VMT los NFTs de varios tickets entre dos cuentas en una sola operación
+/// (todos o ninguno) y devuelve si tuvo éxito
+async fn transfer_ticket_nfts(vmt_contract_id: ActorId, from: ActorId, to: ActorId, ticket_ids: &[U256]) -> bool {
+    let amounts = ticket_ids.iter().map(|_| NFT_COUNT).collect();
+    let transfer_request = vmt_io::BatchTransferFrom::encode_params_with_prefix(
+        "Vmt",
+        from,
+        to,
+        ticket_ids.to_vec(),
+        amounts,
+    );
+    match msg::send_bytes_for_reply(vmt_contract_id, transfer_request, 0, 5_000_000_000) {
+        Ok(reply) => reply.await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Quema en VMT el NFT de un ticket y devuelve si tuvo éxito
+async fn burn_ticket_nft(vmt_contract_id: ActorId, holder: ActorId, ticket_id: U256) -> bool {
+    let burn_request = vmt_io::Burn::encode_params_with_prefix("Vmt", holder, ticket_id, NFT_COUNT);
+    match msg::send_bytes_for_reply(vmt_contract_id, burn_request, 0, 5_000_000_000) {
+        Ok(reply) => reply.await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Devuelve fondos en escrow a una cuenta.
+/// Si el token rechaza la transferencia, el monto queda en su saldo reclamable.
+async fn refund_escrow(storage: &mut Storage, currency: Option<ActorId>, to: ActorId, amount: U256) {
+    if amount.is_zero() {
+        return;
+    }
+    match currency {
+        None => storage.refund_value(to, amount.as_u128()).await,
+        Some(token) => {
+            if !vft_transfer(token, to, amount).await {
+                storage.credit(to, currency, amount);
+            }
+        }
+    }
+}
+
+/// Elige `count` asientos de `free` (en orden de preferencia): el primer bloque
+/// contiguo dentro de una misma fila, o si no hay, los primeros asientos libres
+fn best_available<'a>(free: &[&'a Seat], count: usize) -> Vec<&'a Seat> {
+    if count == 0 || free.len() < count {
+        return Vec::new();
+    }
+    let by_position: HashMap<(&str, &str, u32), &'a Seat> = free
+        .iter()
+        .map(|seat| ((seat.section.as_str(), seat.row.as_str(), seat.number), *seat))
+        .collect();
+    for anchor in free.iter() {
+        // Bloques de la fila del asiento que lo contienen, empezando por el más cercano
+        let first = anchor.number.saturating_sub(count as u32 - 1);
+        for start in (first..=anchor.number).rev() {
+            let block: Vec<&'a Seat> = (0..count as u32)
+                .map_while(|offset| {
+                    let number = start.checked_add(offset)?;
+                    by_position
+                        .get(&(anchor.section.as_str(), anchor.row.as_str(), number))
+                        .copied()
+                })
+                .collect();
+            if block.len() == count {
+                return block;
+            }
+        }
+    }
+    free[..count].to_vec()
+}
+
+/// Reparte el precio de un paquete entre sus `count` tickets en partes iguales;
+/// el resto de la división se asigna al primer ticket
+fn bundle_prices(price: U256, count: usize) -> Vec<U256> {
+    let share = price / U256::from(count);
+    let remainder = price - share * U256::from(count);
+    (0..count)
+        .map(|i| if i == 0 { share + remainder } else { share })
+        .collect()
+}
+
+/// Envía VARA nativo a una cuenta y devuelve si tuvo éxito (no envía nada si el monto es 0).
+/// Espera la respuesta: que el mensaje se encole no garantiza que el destino acepte el valor
+async fn send_value(to: ActorId, amount: u128) -> bool {
+    if amount == 0 {
+        return true;
+    }
+    match msg::send_bytes_for_reply(to, b"", amount, 5_000_000_000) {
+        Ok(reply) => reply.await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Ejecuta `TransferFrom` en un contrato extended-vft y devuelve si tuvo éxito
+/// (un error de envío o de respuesta cuenta como fallo; nunca entra en pánico)
+async fn vft_transfer_from(token: ActorId, from: ActorId, to: ActorId, amount: U256) -> bool {
+    let request = vft_io::TransferFrom::encode_params_with_prefix("Vft", from, to, amount);
+    let reply = match msg::send_bytes_for_reply(token, request, 0, 5_000_000_000) {
+        Ok(reply) => reply.await,
+        Err(_) => return false,
+    };
+    match reply {
+        Ok(reply) => vft_io::TransferFrom::decode_reply_with_prefix("Vft", reply).unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// Ejecuta `Transfer` desde el contrato en un contrato extended-vft y devuelve si tuvo éxito
+/// (un error de envío o de respuesta cuenta como fallo; nunca entra en pánico)
+async fn vft_transfer(token: ActorId, to: ActorId, amount: U256) -> bool {
+    let request = vft_io::Transfer::encode_params_with_prefix("Vft", to, amount);
+    let reply = match msg::send_bytes_for_reply(token, request, 0, 5_000_000_000) {
+        Ok(reply) => reply.await,
+        Err(_) => return false,
+    };
+    match reply {
+        Ok(reply) => vft_io::Transfer::decode_reply_with_prefix("Vft", reply).unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
 /// Estadísticas de un evento
 #[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
 #[codec(crate = sails_rs::scale_codec)]
@@ -1235,6 +5078,64 @@ pub struct EventStats {
     pub active: bool,
 }
 
+/// Estadísticas financieras de un evento (en la moneda del evento)
+#[derive(Debug, Clone, Default, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct EventFinancials {
+    pub event_id: U256,
+    pub primary_revenue: U256, // Recaudación de venta primaria on-chain
+    pub secondary_volume: U256, // Volumen de reventa
+    pub resale_count: u64,
+    pub organizer_royalties: U256, // Comisiones de reventa acumuladas por el organizador
+    pub active_listings: u64,
+    pub floor_listing_price: Option<U256>, // Precio vigente más bajo entre los listados activos
+    pub unique_holders: u64,
+}
+
+/// Comisiones de plataforma en una moneda
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct PlatformRevenue {
+    pub currency: Option<ActorId>,
+    pub total: U256, // Total histórico cobrado
+    pub accrued: U256, // Pendiente de retiro
+}
+
+/// Filtros de `query_listings` (None = sin filtro)
+#[derive(Debug, Clone, Default, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct ListingFilter {
+    pub event_id: Option<U256>,
+    pub zone: Option<String>,
+    pub seller: Option<ActorId>,
+    pub min_price: Option<U256>, // Sobre el precio vigente
+    pub max_price: Option<U256>,
+}
+
+/// Orden de los resultados de `query_listings`
+#[derive(Debug, Clone, Copy, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub enum ListingSort {
+    PriceAsc,
+    PriceDesc,
+    ListedAtAsc,
+    ListedAtDesc,
+}
+
+/// Página de resultados de `query_listings`
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct ListingPage {
+    pub listings: Vec<Listing>,
+    pub next_cursor: Option<u32>, // None si no hay más resultados
+    pub total: u32, // Total de listados que cumplen los filtros
+}
+
 /// Estado público del contrato para consultas
 #[derive(Debug, Default, PartialEq, Eq, Encode, Decode, TypeInfo)]
 #[codec(crate = sails_rs::scale_codec)]
@@ -1251,6 +5152,17 @@ pub struct State {
     pub organizers: Vec<ActorId>,
     pub scanners: Vec<ActorId>,
     pub listings: Vec<(U256, Listing)>,
+    pub balances: Vec<((ActorId, Option<ActorId>), U256)>,
+    pub primary_escrow: Vec<(U256, U256)>,
+    pub platform_revenue_total: Vec<(Option<ActorId>, U256)>,
+    pub platform_revenue_by_event: Vec<(U256, U256)>,
+    pub platform_accrued: Vec<(Option<ActorId>, U256)>,
+    pub event_financials: Vec<(U256, EventFinancials)>,
+    pub offer_id_counter: U256,
+    pub offers: Vec<(U256, Offer)>,
+    pub auctions: Vec<(U256, Auction)>,
+    pub bundle_id_counter: U256,
+    pub bundles: Vec<(U256, Bundle)>,
 }
 
 impl From<Storage> for State {
@@ -1266,7 +5178,408 @@ impl From<Storage> for State {
             event_tickets: value.event_tickets.into_iter().collect(),
             organizers: value.organizers.into_iter().collect(),
             scanners: value.scanners.into_iter().collect(),
-            listings: value.listings.into_iter().collect(),
+            listings: value
+                .listings
+                .into_iter()
+                .filter(|(_, listing)| listing.is_public())
+                .collect(),
+            balances: value.balances.into_iter().collect(),
+            primary_escrow: value.primary_escrow.into_iter().collect(),
+            platform_revenue_total: value.platform_revenue_total.into_iter().collect(),
+            platform_revenue_by_event: value.platform_revenue_by_event.into_iter().collect(),
+            platform_accrued: value.platform_accrued.into_iter().collect(),
+            event_financials: value.event_financials.into_iter().collect(),
+            offer_id_counter: value.offer_id_counter,
+            offers: value.offers.into_iter().collect(),
+            auctions: value.auctions.into_iter().collect(),
+            bundle_id_counter: value.bundle_id_counter,
+            bundles: value.bundles.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn commission(seller: u16, organizer: u16, platform: u16, beneficiaries: Vec<(ActorId, u16)>) -> CommissionConfig {
+        CommissionConfig {
+            seller_percentage: seller,
+            organizer_percentage: organizer,
+            platform_percentage: platform,
+            beneficiaries,
+        }
+    }
+    
+    fn split_total(split: &SaleSplit) -> U256 {
+        split
+            .beneficiary_shares
+            .iter()
+            .fold(split.seller_share + split.organizer_share + split.platform_share, |total, (_, amount)| {
+                total + *amount
+            })
+    }
+    
+    #[test]
+    fn split_assigns_rounding_remainder_to_seller() {
+        let split = commission(9000, 700, 300, vec![]).split(U256::from(1001));
+        
+        assert_eq!(split.organizer_share, U256::from(70));
+        assert_eq!(split.platform_share, U256::from(30));
+        assert_eq!(split.seller_share, U256::from(901));
+        assert_eq!(split.rounding_remainder, U256::one());
+        assert_eq!(split_total(&split), U256::from(1001));
+    }
+    
+    #[test]
+    fn split_without_remainder() {
+        let split = commission(9000, 700, 300, vec![]).split(U256::from(10_000));
+        
+        assert_eq!(split.seller_share, U256::from(9000));
+        assert_eq!(split.organizer_share, U256::from(700));
+        assert_eq!(split.platform_share, U256::from(300));
+        assert_eq!(split.rounding_remainder, U256::zero());
+    }
+    
+    #[test]
+    fn split_of_tiny_price_goes_to_seller() {
+        let split = commission(9000, 700, 300, vec![]).split(U256::from(9));
+        
+        assert_eq!(split.organizer_share, U256::zero());
+        assert_eq!(split.platform_share, U256::zero());
+        assert_eq!(split.seller_share, U256::from(9));
+        assert_eq!(split.rounding_remainder, U256::from(1));
+    }
+    
+    fn dutch_listing(start_price: u64, floor_price: u64, listed_at: u64, ends_at: u64) -> Listing {
+        Listing {
+            ticket_id: U256::one(),
+            seller: ActorId::from(1),
+            price: U256::from(start_price),
+            listed_at,
+            event_id: U256::one(),
+            kind: ListingKind::Dutch {
+                floor_price: U256::from(floor_price),
+                ends_at,
+            },
+            expires_at: None,
+            reserved_for: None,
+        }
+    }
+    
+    #[test]
+    fn dutch_price_declines_linearly() {
+        let listing = dutch_listing(1000, 400, 100, 200);
+        
+        assert_eq!(listing.current_price(100), U256::from(1000));
+        assert_eq!(listing.current_price(150), U256::from(700));
+        assert_eq!(listing.current_price(175), U256::from(550));
+    }
+    
+    #[test]
+    fn dutch_price_stays_at_floor_after_end() {
+        let listing = dutch_listing(1000, 400, 100, 200);
+        
+        assert_eq!(listing.current_price(200), U256::from(400));
+        assert_eq!(listing.current_price(10_000), U256::from(400));
+    }
+    
+    #[test]
+    fn dutch_price_before_listing_is_start_price() {
+        let listing = dutch_listing(1000, 400, 100, 200);
+        
+        assert_eq!(listing.current_price(50), U256::from(1000));
+    }
+    
+    #[test]
+    fn fixed_price_does_not_change() {
+        let listing = Listing {
+            kind: ListingKind::FixedPrice,
+            ..dutch_listing(1000, 400, 100, 200)
+        };
+        
+        assert_eq!(listing.current_price(150), U256::from(1000));
+        assert_eq!(listing.current_price(10_000), U256::from(1000));
+    }
+    
+    #[test]
+    fn floor_listing_price_follows_dutch_decline() {
+        let mut storage = Storage::default();
+        storage.insert_listing(dutch_listing(1000, 400, 100, 200));
+        storage.insert_listing(Listing {
+            ticket_id: U256::from(2),
+            kind: ListingKind::FixedPrice,
+            ..dutch_listing(800, 400, 100, 200)
+        });
+        
+        assert_eq!(storage.floor_listing_price(U256::one(), 100), Some(U256::from(800)));
+        assert_eq!(storage.floor_listing_price(U256::one(), 200), Some(U256::from(400)));
+        
+        storage.remove_listing(&U256::one());
+        assert_eq!(storage.floor_listing_price(U256::one(), 200), Some(U256::from(800)));
+        storage.remove_listing(&U256::from(2));
+        assert_eq!(storage.floor_listing_price(U256::one(), 200), None);
+    }
+    
+    fn event_config(resale_config: ResaleConfig, primary_price: Option<u64>, zones: Vec<EventZone>) -> EventConfig {
+        EventConfig {
+            event_id: U256::one(),
+            organizer: ActorId::from(1),
+            metadata_hash: [0; 32],
+            event_start_time: 1_000_000,
+            tickets_minted: U256::zero(),
+            tickets_total: U256::from(100),
+            resale_config,
+            commission_config: commission(9000, 700, 300, vec![]),
+            active: true,
+            payment_token: None,
+            primary_price: primary_price.map(U256::from),
+            zones,
+            cancelled: false,
+            wallet_limits: WalletLimits::default(),
+            fan_exchange: false,
+        }
+    }
+    
+    fn resale_config(max_price: Option<u64>, min_price: Option<u64>, max_markup_bps: Option<u32>) -> ResaleConfig {
+        ResaleConfig {
+            enabled: true,
+            max_price: max_price.map(U256::from),
+            min_price: min_price.map(U256::from),
+            max_markup_bps,
+            resale_start_time: None,
+            resale_end_time: None,
+        }
+    }
+    
+    fn zone(name: &str, face_price: Option<u64>, seats: Vec<Seat>) -> EventZone {
+        EventZone {
+            zone_id: 1,
+            name: name.into(),
+            capacity: U256::from(100),
+            face_price: face_price.map(U256::from),
+            minted: U256::zero(),
+            seats,
+        }
+    }
+    
+    fn ticket(zone: Option<&str>, purchase_price: u64) -> Ticket {
+        Ticket {
+            ticket_id: U256::one(),
+            event_id: U256::one(),
+            zone: zone.map(Into::into),
+            seat: None,
+            original_buyer: ActorId::from(2),
+            current_owner: ActorId::from(2),
+            used: false,
+            minted_at: 0,
+            purchase_price: U256::from(purchase_price),
+        }
+    }
+    
+    #[test]
+    fn resale_price_respects_max_and_min() {
+        let config = event_config(resale_config(Some(1000), Some(100), None), None, vec![]);
+        let ticket = ticket(None, 0);
+        
+        assert_eq!(config.check_resale_price(U256::from(1000), &ticket), Ok(()));
+        assert_eq!(config.check_resale_price(U256::from(100), &ticket), Ok(()));
+        assert_eq!(
+            config.check_resale_price(U256::from(1001), &ticket),
+            Err(TicketError::PriceExceedsMaximum)
+        );
+        assert_eq!(
+            config.check_resale_price(U256::from(99), &ticket),
+            Err(TicketError::PriceBelowMinimum)
+        );
+    }
+    
+    #[test]
+    fn resale_markup_is_measured_against_purchase_price() {
+        let config = event_config(resale_config(None, None, Some(2000)), Some(500), vec![]);
+        let ticket = ticket(None, 1000);
+        
+        assert_eq!(config.check_resale_price(U256::from(1200), &ticket), Ok(()));
+        assert_eq!(
+            config.check_resale_price(U256::from(1201), &ticket),
+            Err(TicketError::MarkupExceedsMaximum)
+        );
+    }
+    
+    #[test]
+    fn resale_markup_falls_back_to_zone_face_price() {
+        let config = event_config(
+            resale_config(None, None, Some(2000)),
+            Some(800),
+            vec![zone("VIP", Some(500), vec![])],
+        );
+        let ticket = ticket(Some("VIP"), 0);
+        
+        assert_eq!(config.check_resale_price(U256::from(600), &ticket), Ok(()));
+        assert_eq!(
+            config.check_resale_price(U256::from(601), &ticket),
+            Err(TicketError::MarkupExceedsMaximum)
+        );
+    }
+    
+    #[test]
+    fn resale_markup_falls_back_to_primary_price_without_zones() {
+        let config = event_config(resale_config(None, None, Some(2000)), Some(800), vec![]);
+        let ticket = ticket(Some("General"), 0);
+        
+        assert_eq!(config.check_resale_price(U256::from(960), &ticket), Ok(()));
+        assert_eq!(
+            config.check_resale_price(U256::from(961), &ticket),
+            Err(TicketError::MarkupExceedsMaximum)
+        );
+    }
+    
+    #[test]
+    fn resale_is_rejected_for_inactive_or_cancelled_event() {
+        let mut config = event_config(resale_config(None, None, None), None, vec![]);
+        let ticket = ticket(None, 100);
+        assert_eq!(config.check_resale(U256::from(100), &ticket, 0), Ok(()));
+        
+        config.active = false;
+        assert_eq!(config.check_resale(U256::from(100), &ticket, 0), Err(TicketError::EventNotActive));
+        
+        config.cancelled = true;
+        assert_eq!(config.check_resale(U256::from(100), &ticket, 0), Err(TicketError::EventCancelled));
+    }
+    
+    #[test]
+    fn resale_markup_without_any_base_is_not_limited() {
+        let config = event_config(resale_config(None, None, Some(2000)), None, vec![]);
+        
+        assert_eq!(config.check_resale_price(U256::from(1_000_000), &ticket(None, 0)), Ok(()));
+    }
+    
+    fn wallet_limits(primary: Option<u32>, holdings: Option<u32>, resales: Option<u32>) -> WalletLimits {
+        WalletLimits {
+            max_primary_purchases: primary,
+            max_holdings: holdings,
+            max_resales: resales,
+        }
+    }
+    
+    #[test]
+    fn wallet_limits_without_caps_allow_everything() {
+        let limits = WalletLimits::default();
+        
+        assert_eq!(limits.check_primary(u32::MAX, u32::MAX, 1), Ok(()));
+        assert_eq!(limits.check_holding(u32::MAX, 1), Ok(()));
+        assert_eq!(limits.check_resale(u32::MAX), Ok(()));
+    }
+    
+    #[test]
+    fn wallet_limits_cap_primary_purchases() {
+        let limits = wallet_limits(Some(4), None, None);
+        
+        assert_eq!(limits.check_primary(2, 0, 2), Ok(()));
+        assert_eq!(limits.check_primary(2, 0, 3), Err(TicketError::PrimaryLimitExceeded));
+    }
+    
+    #[test]
+    fn wallet_limits_cap_holdings() {
+        let limits = wallet_limits(None, Some(3), None);
+        
+        assert_eq!(limits.check_holding(2, 1), Ok(()));
+        assert_eq!(limits.check_holding(2, 2), Err(TicketError::HoldingLimitExceeded));
+        // La compra primaria también respeta la tenencia
+        assert_eq!(limits.check_primary(0, 3, 1), Err(TicketError::HoldingLimitExceeded));
+    }
+    
+    #[test]
+    fn wallet_limits_cap_resales() {
+        let limits = wallet_limits(None, None, Some(2));
+        
+        assert_eq!(limits.check_resale(1), Ok(()));
+        assert_eq!(limits.check_resale(2), Err(TicketError::ResaleLimitExceeded));
+    }
+    
+    #[test]
+    fn bundle_prices_split_evenly_with_remainder_on_first() {
+        assert_eq!(
+            bundle_prices(U256::from(100), 3),
+            vec![U256::from(34), U256::from(33), U256::from(33)]
+        );
+        assert_eq!(bundle_prices(U256::from(100), 4), vec![U256::from(25); 4]);
+    }
+    
+    #[test]
+    fn bundle_prices_add_up_to_bundle_price() {
+        let prices = bundle_prices(U256::from(1_000_003), 7);
+        
+        assert_eq!(prices.len(), 7);
+        assert_eq!(prices.iter().fold(U256::zero(), |total, price| total + *price), U256::from(1_000_003));
+    }
+    
+    #[test]
+    fn bundle_expires_when_resale_closes() {
+        let bundle = Bundle {
+            bundle_id: U256::one(),
+            event_id: U256::one(),
+            seller: ActorId::from(2),
+            ticket_ids: vec![U256::one(), U256::from(2)],
+            price: U256::from(100),
+            listed_at: 0,
+        };
+        let mut config = event_config(resale_config(None, None, None), None, vec![]);
+        config.resale_config.resale_end_time = Some(500);
+        
+        assert!(!bundle.is_expired(&config, 500));
+        assert!(bundle.is_expired(&config, 501));
+        
+        config.resale_config.resale_end_time = None;
+        assert!(!bundle.is_expired(&config, config.event_start_time - 1));
+        assert!(bundle.is_expired(&config, config.event_start_time));
+    }
+    
+    #[test]
+    fn split_pays_beneficiaries_rounded_down() {
+        let artist = ActorId::from(10);
+        let venue = ActorId::from(11);
+        let split = commission(8500, 700, 300, vec![(artist, 300), (venue, 200)]).split(U256::from(1001));
+        
+        assert_eq!(split.beneficiary_shares, vec![(artist, U256::from(30)), (venue, U256::from(20))]);
+        assert_eq!(split.seller_share, U256::from(851));
+        assert_eq!(split.rounding_remainder, U256::one());
+        assert_eq!(split_total(&split), U256::from(1001));
+    }
+    
+    fn seat(row: &str, number: u32) -> Seat {
+        Seat {
+            section: "Platea".into(),
+            row: row.into(),
+            number,
         }
     }
+    
+    #[test]
+    fn best_available_picks_first_contiguous_block() {
+        let seats = [seat("A", 1), seat("A", 3), seat("A", 4), seat("A", 5)];
+        let free: Vec<&Seat> = seats.iter().collect();
+        
+        assert_eq!(best_available(&free, 2), vec![&seats[1], &seats[2]]);
+        assert_eq!(best_available(&free, 3), vec![&seats[1], &seats[2], &seats[3]]);
+        assert_eq!(best_available(&free, 1), vec![&seats[0]]);
+    }
+    
+    #[test]
+    fn best_available_does_not_join_rows() {
+        let seats = [seat("A", 10), seat("B", 11), seat("C", 1)];
+        let free: Vec<&Seat> = seats.iter().collect();
+        
+        // Sin bloque contiguo en una fila: los primeros asientos libres
+        assert_eq!(best_available(&free, 2), vec![&seats[0], &seats[1]]);
+    }
+    
+    #[test]
+    fn best_available_without_enough_seats_is_empty() {
+        let seats = [seat("A", 1), seat("A", 2)];
+        let free: Vec<&Seat> = seats.iter().collect();
+        
+        assert!(best_available(&free, 3).is_empty());
+        assert!(best_available(&free, 0).is_empty());
+    }
 }
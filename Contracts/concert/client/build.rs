@@ -1,16 +1,42 @@
 use sails_client_gen::ClientGenerator;
-use std::{env, path::PathBuf};
+use std::{env, fs, path::PathBuf};
 
 fn main() {
     let out_dir_path = PathBuf::from(env::var("OUT_DIR").unwrap());
     let idl_file_path = out_dir_path.join("concert.idl");
 
     // Generate IDL file for the program
-    sails_idl_gen::generate_idl_to_file::<concert_app::ConcertProgram>(&idl_file_path).unwrap();
+    sails_idl_gen::generate_idl_to_file::<concert_app::TicketProgram>(&idl_file_path).unwrap();
+
+    // The `Ticket` type would clash with the client of the `Ticket` service,
+    // so the client exposes it as `TicketInfo`
+    let idl = fs::read_to_string(&idl_file_path).unwrap();
+    fs::write(&idl_file_path, rename_ticket_type(&idl)).unwrap();
 
     // Generate client code from IDL file
     ClientGenerator::from_idl_path(&idl_file_path)
         .with_mocks("mocks")
-        .generate_to(PathBuf::from(env::var("OUT_DIR").unwrap()).join("concert_client.rs"))
+        .generate_to(out_dir_path.join("concert_client.rs"))
         .unwrap();
 }
+
+/// Renames every use of the `Ticket` type in the IDL, leaving the service name untouched
+fn rename_ticket_type(idl: &str) -> String {
+    let mut renamed = String::with_capacity(idl.len());
+    let mut word = String::new();
+    for ch in idl.chars().chain(Some('\n')) {
+        if ch.is_alphanumeric() || ch == '_' {
+            word.push(ch);
+            continue;
+        }
+        if word == "Ticket" && !renamed.ends_with("service ") {
+            renamed.push_str("TicketInfo");
+        } else {
+            renamed.push_str(&word);
+        }
+        word.clear();
+        renamed.push(ch);
+    }
+    renamed.pop();
+    renamed
+}
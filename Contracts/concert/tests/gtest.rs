@@ -1,21 +1,248 @@
 use concert_client::{
-    traits::{Concert, ConcertFactory},
-    Concert as ConcertClient, ConcertFactory as Factory, TokenMetadata,
+    traits::{ConcertFactory as _, Market as _, Ticket as _},
+    CommissionConfig, ConcertFactory as Factory, ListingFilter, ListingSort,
+    Market as MarketClient, ResaleConfig, Ticket as TicketClient, TicketInfo, WalletLimits,
+    ZoneConfig,
 };
 use extended_vmt_client::vmt::io as vmt_io;
 use sails_rs::{
     calls::*,
+    errors::Result,
     gtest::{calls::*, Program, System},
     ActorId, Encode, U256,
 };
 
-pub const USER_ID: u64 = 10;
-pub const TOKEN_ID: U256 = U256::one();
-pub const CONCERT_ID: U256 = U256::zero();
-pub const AMOUNT: U256 = U256::one();
-pub const DATE: u128 = 100000;
+pub const USER_ID: u64 = 10; // Admin, event organizer and VMT deployer
+pub const SELLER_ID: u64 = 11;
+pub const BUYER_ID: u64 = 12;
+pub const FEE_RECIPIENT_ID: u64 = 13;
+pub const OTHER_ID: u64 = 14;
+pub const EVENT_ID: U256 = U256::one();
+pub const TICKET_ID: U256 = U256::one();
+pub const PRICE: u128 = 10_000_000_000_000;
+pub const RESALE_PRICE: u128 = 20_000_000_000_000;
+pub const START_DELAY: u64 = 60 * 60 * 1000; // The event starts one hour after it is created
+pub const MINUTE: u64 = 60 * 1000;
 
-fn init_multitoken(sys: &System) -> (ActorId, Program<'_>) {
+/// Concert program deployed with its VMT contract, shared by every test
+struct Fixture {
+    program_space: GTestRemoting,
+    concert_id: ActorId,
+    vmt_id: ActorId,
+}
+
+impl Fixture {
+    /// Deploys VMT and the concert program, grants it the VMT roles and funds the test accounts
+    async fn new() -> Self {
+        let system = System::new();
+        system.init_logger();
+        for id in [USER_ID, SELLER_ID, BUYER_ID, FEE_RECIPIENT_ID, OTHER_ID] {
+            system.mint_to(id, 1_000_000_000_000_000);
+        }
+        let program_space = GTestRemoting::new(system, USER_ID.into());
+        let code_id = program_space
+            .system()
+            .submit_code_file("../target/wasm32-gear/release/concert.opt.wasm");
+
+        let vmt_id = init_multitoken(program_space.system());
+        let concert_id = Factory::new(program_space.clone())
+            .new(USER_ID.into(), vmt_id, FEE_RECIPIENT_ID.into())
+            .send_recv(code_id, "123")
+            .await
+            .unwrap();
+
+        let fixture = Self {
+            program_space,
+            concert_id,
+            vmt_id,
+        };
+        fixture.send_vmt(
+            USER_ID,
+            vmt_io::GrantMinterRole::encode_params_with_prefix("Vmt", concert_id),
+        );
+        fixture.send_vmt(
+            USER_ID,
+            vmt_io::GrantBurnerRole::encode_params_with_prefix("Vmt", concert_id),
+        );
+        fixture
+    }
+
+    fn system(&self) -> &System {
+        self.program_space.system()
+    }
+
+    /// Ticket service client acting as `actor`
+    fn ticket(&self, actor: u64) -> TicketClient<GTestRemoting> {
+        TicketClient::new(self.program_space.clone().with_actor_id(actor.into()))
+    }
+
+    /// Market service client acting as `actor`
+    fn market(&self, actor: u64) -> MarketClient<GTestRemoting> {
+        MarketClient::new(self.program_space.clone().with_actor_id(actor.into()))
+    }
+
+    /// Creates an event sold in VARA with `PRICE` as primary price and a 85% / 10% / 5%
+    /// commission split, starting `START_DELAY` after now
+    async fn create_event(&self) {
+        self.create_event_with_zones(Vec::new()).await;
+    }
+
+    async fn create_event_with_zones(&self, zones: Vec<ZoneConfig>) {
+        let resale_config = ResaleConfig {
+            enabled: true,
+            max_price: None,
+            min_price: None,
+            max_markup_bps: None,
+            resale_start_time: None,
+            resale_end_time: None,
+        };
+        let commission_config = CommissionConfig {
+            seller_percentage: 8500,
+            organizer_percentage: 1000,
+            platform_percentage: 500,
+            beneficiaries: vec![],
+        };
+        let wallet_limits = WalletLimits {
+            max_primary_purchases: None,
+            max_holdings: None,
+            max_resales: None,
+        };
+        self.ticket(USER_ID)
+            .create_event(
+                USER_ID.into(),
+                [0u8; 32],
+                self.now() + START_DELAY,
+                U256::from(100),
+                resale_config,
+                commission_config,
+                None,
+                Some(U256::from(PRICE)),
+                zones,
+                wallet_limits,
+            )
+            .send_recv(self.concert_id)
+            .await
+            .unwrap();
+    }
+
+    /// The organizer mints `amount` tickets of `event_id` to `owner` (sold off-chain)
+    async fn mint(&self, event_id: U256, owner: u64, amount: u64) {
+        self.ticket(USER_ID)
+            .mint_tickets(event_id, owner.into(), U256::from(amount), vec![], vec![])
+            .send_recv(self.concert_id)
+            .await
+            .unwrap();
+    }
+
+    /// `buyer` buys `amount` tickets of the event in the primary sale
+    async fn buy_primary(&self, buyer: u64, amount: u64) {
+        self.ticket(buyer)
+            .buy_primary(EVENT_ID, None, U256::from(amount), vec![])
+            .with_value(PRICE * amount as u128)
+            .send_recv(self.concert_id)
+            .await
+            .unwrap();
+    }
+
+    /// `seller` lists a ticket at a fixed price
+    async fn list(&self, seller: u64, ticket_id: U256, price: u128) {
+        self.market(seller)
+            .list_ticket(ticket_id, U256::from(price), None, None)
+            .send_recv(self.concert_id)
+            .await
+            .unwrap();
+    }
+
+    /// `buyer` buys a listed ticket for themselves attaching `value`
+    async fn buy(&self, buyer: u64, ticket_id: U256, value: u128) -> Result<()> {
+        self.market(buyer)
+            .buy_ticket(buyer.into(), ticket_id)
+            .with_value(value)
+            .send_recv(self.concert_id)
+            .await
+    }
+
+    async fn ticket_info(&self, ticket_id: U256) -> Option<TicketInfo> {
+        self.ticket(USER_ID)
+            .get_ticket(ticket_id)
+            .recv(self.concert_id)
+            .await
+            .unwrap()
+    }
+
+    async fn owner(&self, ticket_id: U256) -> Option<ActorId> {
+        self.ticket_info(ticket_id)
+            .await
+            .map(|ticket| ticket.current_owner)
+    }
+
+    async fn claimable(&self, account: u64) -> U256 {
+        self.market(USER_ID)
+            .get_claimable_balance(account.into(), None)
+            .recv(self.concert_id)
+            .await
+            .unwrap()
+    }
+
+    /// VARA held by the concert program (escrows and claimable balances)
+    fn concert_balance(&self) -> u128 {
+        self.system().balance_of(self.concert_id)
+    }
+
+    fn now(&self) -> u64 {
+        self.system().block_timestamp()
+    }
+
+    /// Runs blocks until the block timestamp is past `timestamp`
+    fn run_past(&self, timestamp: u64) {
+        while self.now() <= timestamp {
+            self.system().run_next_block();
+        }
+    }
+
+    /// Runs blocks until the event has started
+    async fn start_event(&self) {
+        let event = self
+            .ticket(USER_ID)
+            .get_event(EVENT_ID)
+            .recv(self.concert_id)
+            .await
+            .unwrap()
+            .unwrap();
+        self.run_past(event.event_start_time);
+    }
+
+    /// `owner` approves the concert program to transfer its tickets in VMT
+    fn approve(&self, owner: u64) {
+        self.send_vmt(
+            owner,
+            vmt_io::Approve::encode_params_with_prefix("Vmt", self.concert_id, true),
+        );
+    }
+
+    fn vmt_balance(&self, account: u64, ticket_id: U256) -> U256 {
+        let reply = self.send_vmt(
+            USER_ID,
+            vmt_io::BalanceOf::encode_params_with_prefix("Vmt", account.into(), ticket_id),
+        );
+        vmt_io::BalanceOf::decode_reply_with_prefix("Vmt", reply.as_slice()).unwrap()
+    }
+
+    /// Sends a VMT call that must succeed and returns its reply payload
+    fn send_vmt(&self, from: u64, payload: Vec<u8>) -> Vec<u8> {
+        let vmt = self.system().get_program(self.vmt_id).unwrap();
+        let mid = vmt.send_bytes(from, payload);
+        let res = self.system().run_next_block();
+        assert!(res.succeed.contains(&mid));
+        res.log
+            .iter()
+            .find(|log| log.reply_to() == Some(mid))
+            .map(|log| log.payload().to_vec())
+            .unwrap_or_default()
+    }
+}
+
+fn init_multitoken(sys: &System) -> ActorId {
     let vmt = Program::from_file(sys, "../target/wasm32-gear/release/extended_vmt.opt.wasm");
     let payload = ("Name".to_string(), "Symbol".to_string(), 10_u8);
     let encoded_request = ["New".encode(), payload.encode()].concat();
@@ -23,200 +250,1177 @@ fn init_multitoken(sys: &System) -> (ActorId, Program<'_>) {
     let res = sys.run_next_block();
     assert!(res.succeed.contains(&mid));
 
-    (vmt.id(), vmt)
+    vmt.id()
 }
-fn grant_roles(sys: &System, vmt: &Program, concert_id: ActorId) {
-    let encoded_request = vmt_io::GrantMinterRole::encode_call(concert_id);
-    let mid = vmt.send_bytes(USER_ID, encoded_request);
-    let res = sys.run_next_block();
-    assert!(res.succeed.contains(&mid));
-    let encoded_request = vmt_io::GrantBurnerRole::encode_call(concert_id);
-    let mid = vmt.send_bytes(USER_ID, encoded_request);
-    let res = sys.run_next_block();
-    assert!(res.succeed.contains(&mid));
+
+/// The seller holds a ticket listed at `RESALE_PRICE` and has approved the concert program
+async fn listed_ticket() -> Fixture {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 1).await;
+    fixture.approve(SELLER_ID);
+    fixture.list(SELLER_ID, TICKET_ID, RESALE_PRICE).await;
+    fixture
 }
 
-fn get_balance(sys: &System, vmt: &Program, account: ActorId, id: U256) -> U256 {
-    let encoded_request = vmt_io::BalanceOf::encode_call(account, id);
-    let mid = vmt.send_bytes(USER_ID, encoded_request);
-    let res = sys.run_next_block();
-    assert!(res.succeed.contains(&mid));
-    vmt_io::BalanceOf::decode_reply(res.log[0].payload()).unwrap()
-}
-#[tokio::test]
-async fn create_concert() {
-    let system = System::new();
-    system.init_logger();
-    system.mint_to(USER_ID, 1_000_000_000_000_000);
-    let program_space = GTestRemoting::new(system, USER_ID.into());
-    let code_id = program_space
-        .system()
-        .submit_code_file("../target/wasm32-gear/release/concert.opt.wasm");
-
-    let concert_factory = Factory::new(program_space.clone());
-    let (vmt_id, _vmt_program) = init_multitoken(program_space.system());
-    let concert_id = concert_factory
-        .new(USER_ID.into(), vmt_id)
-        .send_recv(code_id, "123")
-        .await
-        .unwrap();
-
-    let mut client = ConcertClient::new(program_space);
-    // create
-    client
-        .create(
-            USER_ID.into(),
-            String::from("Sum 41"),
-            String::from("Sum 41 concert in Madrid. 26/08/2022"),
-            U256::from(100),
-            DATE,
-            TOKEN_ID,
-        )
-        .send_recv(concert_id)
+fn no_filter() -> ListingFilter {
+    ListingFilter {
+        event_id: None,
+        zone: None,
+        seller: None,
+        min_price: None,
+        max_price: None,
+    }
+}
+
+fn ticket_ids(listings: &[concert_client::Listing]) -> Vec<U256> {
+    listings.iter().map(|listing| listing.ticket_id).collect()
+}
+
+#[tokio::test]
+async fn create_event_and_mint_tickets() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 2).await;
+
+    let event = fixture
+        .ticket(USER_ID)
+        .get_event(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.tickets_minted, U256::from(2));
+    for ticket_id in [TICKET_ID, TICKET_ID + 1] {
+        assert_eq!(fixture.owner(ticket_id).await, Some(SELLER_ID.into()));
+        assert_eq!(fixture.vmt_balance(SELLER_ID, ticket_id), 1.into());
+    }
+
+    // Only organizers mint tickets
+    assert!(fixture
+        .ticket(SELLER_ID)
+        .mint_tickets(EVENT_ID, SELLER_ID.into(), U256::one(), vec![], vec![])
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn resale_pays_seller_organizer_and_platform() {
+    let fixture = listed_ticket().await;
+
+    fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
         .await
         .unwrap();
-    // check state
-    let state = client.get_storage().recv(concert_id).await.unwrap();
 
-    assert_eq!(state.name, "Sum 41".to_string());
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(BUYER_ID.into()));
+    assert_eq!(fixture.vmt_balance(BUYER_ID, TICKET_ID), 1.into());
+    assert_eq!(fixture.vmt_balance(SELLER_ID, TICKET_ID), 0.into());
+
+    // The price is credited to the claimable balances
     assert_eq!(
-        state.description,
-        "Sum 41 concert in Madrid. 26/08/2022".to_string()
+        fixture.claimable(SELLER_ID).await,
+        U256::from(RESALE_PRICE / 100 * 85)
     );
-    assert_eq!(state.date, DATE);
-    assert_eq!(state.tickets_left, U256::from(100));
-}
-
-#[tokio::test]
-async fn buy_tickets() {
-    let system = System::new();
-    system.init_logger();
-    system.mint_to(USER_ID, 1_000_000_000_000_000);
-    let program_space = GTestRemoting::new(system, USER_ID.into());
-    let code_id = program_space
-        .system()
-        .submit_code_file("../target/wasm32-gear/release/concert.opt.wasm");
-
-    let concert_factory = Factory::new(program_space.clone());
-    let (vmt_id, vmt_program) = init_multitoken(program_space.system());
-    let concert_id = concert_factory
-        .new(USER_ID.into(), vmt_id)
-        .send_recv(code_id, "123")
-        .await
-        .unwrap();
-    grant_roles(program_space.system(), &vmt_program, concert_id);
-    let mut client = ConcertClient::new(program_space.clone());
-    // create
-    client
-        .create(
-            USER_ID.into(),
-            String::from("Sum 41"),
-            String::from("Sum 41 concert in Madrid. 26/08/2022"),
-            U256::from(100),
-            DATE,
-            TOKEN_ID,
-        )
-        .send_recv(concert_id)
+    assert_eq!(
+        fixture.claimable(USER_ID).await,
+        U256::from(RESALE_PRICE / 100 * 10)
+    );
+    let platform = fixture
+        .ticket(USER_ID)
+        .get_platform_revenue(None)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(platform.accrued, U256::from(RESALE_PRICE / 100 * 5));
+}
+
+#[tokio::test]
+async fn resale_without_enough_value_fails() {
+    let fixture = listed_ticket().await;
+
+    assert!(fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE - 1)
+        .await
+        .is_err());
+
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(SELLER_ID.into()));
+    assert_eq!(fixture.claimable(SELLER_ID).await, U256::zero());
+}
+
+#[tokio::test]
+async fn failed_transfer_restores_the_listing_and_returns_the_payment() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 1).await;
+    fixture.list(SELLER_ID, TICKET_ID, RESALE_PRICE).await;
+    let concert_balance = fixture.concert_balance();
+
+    // Without the seller's approval the VMT transfer fails after the payment was taken
+    fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .unwrap();
+
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(SELLER_ID.into()));
+    let listing = fixture
+        .market(USER_ID)
+        .get_listing(TICKET_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(listing.is_some());
+    assert_eq!(fixture.claimable(SELLER_ID).await, U256::zero());
+    assert_eq!(fixture.concert_balance(), concert_balance);
+
+    // The contract is not left locked: the purchase goes through once approved
+    fixture.approve(SELLER_ID);
+    fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .unwrap();
+
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(BUYER_ID.into()));
+}
+
+#[tokio::test]
+async fn primary_sale_is_escrowed_until_the_event_starts() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    let concert_balance = fixture.concert_balance();
+
+    // The excess over the price is returned
+    fixture
+        .ticket(BUYER_ID)
+        .buy_primary(EVENT_ID, None, U256::from(2), vec![])
+        .with_value(PRICE * 3)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    let tickets = fixture
+        .ticket(USER_ID)
+        .get_user_tickets(BUYER_ID.into())
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(tickets.len(), 2);
+    assert!(tickets
+        .iter()
+        .all(|ticket| ticket.purchase_price == U256::from(PRICE)));
+    assert_eq!(fixture.vmt_balance(BUYER_ID, TICKET_ID), 1.into());
+    assert_eq!(fixture.vmt_balance(BUYER_ID, TICKET_ID + 1), 1.into());
+    assert_eq!(fixture.concert_balance(), concert_balance + PRICE * 2);
+
+    // Only the price is kept in escrow, nothing is credited to the organizer yet
+    let escrow = fixture
+        .ticket(USER_ID)
+        .get_primary_escrow(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(escrow, U256::from(PRICE * 2));
+    assert_eq!(fixture.claimable(USER_ID).await, U256::zero());
+
+    assert!(fixture
+        .ticket(USER_ID)
+        .release_primary_revenue(EVENT_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+
+    fixture.start_event().await;
+    fixture
+        .ticket(USER_ID)
+        .release_primary_revenue(EVENT_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    let escrow = fixture
+        .ticket(USER_ID)
+        .get_primary_escrow(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(escrow, U256::zero());
+    assert_eq!(fixture.claimable(USER_ID).await, U256::from(PRICE * 2));
+}
+
+#[tokio::test]
+async fn primary_sale_closes_when_the_event_starts() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.start_event().await;
+    let concert_balance = fixture.concert_balance();
+
+    assert!(fixture
+        .ticket(BUYER_ID)
+        .buy_primary(EVENT_ID, None, U256::one(), vec![])
+        .with_value(PRICE)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    assert_eq!(fixture.concert_balance(), concert_balance);
+}
+
+#[tokio::test]
+async fn withdraw_pays_out_the_claimable_balance_once() {
+    let fixture = listed_ticket().await;
+    fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .unwrap();
+
+    let seller_share = RESALE_PRICE / 100 * 85;
+    let concert_balance = fixture.concert_balance();
+    fixture
+        .market(SELLER_ID)
+        .withdraw(None)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    assert_eq!(fixture.claimable(SELLER_ID).await, U256::zero());
+    assert_eq!(fixture.concert_balance(), concert_balance - seller_share);
+
+    // Nothing left to withdraw
+    assert!(fixture
+        .market(SELLER_ID)
+        .withdraw(None)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn cancelled_event_refunds_primary_buyers() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.buy_primary(BUYER_ID, 1).await;
+
+    fixture
+        .ticket(USER_ID)
+        .cancel_event(EVENT_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    fixture
+        .ticket(BUYER_ID)
+        .claim_refund(TICKET_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    // The ticket is burned and its price moves from escrow to the holder's balance
+    assert!(fixture.ticket_info(TICKET_ID).await.is_none());
+    assert_eq!(fixture.vmt_balance(BUYER_ID, TICKET_ID), 0.into());
+    let escrow = fixture
+        .ticket(USER_ID)
+        .get_primary_escrow(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(escrow, U256::zero());
+    assert_eq!(fixture.claimable(BUYER_ID).await, U256::from(PRICE));
+
+    assert!(fixture
+        .ticket(BUYER_ID)
+        .claim_refund(TICKET_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn minted_tickets_have_nothing_to_refund() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, BUYER_ID, 1).await;
+    fixture
+        .ticket(USER_ID)
+        .cancel_event(EVENT_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    // Tickets sold off-chain are refunded off-chain: the claim fails and the ticket is kept
+    assert!(fixture
+        .ticket(BUYER_ID)
+        .claim_refund(TICKET_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(BUYER_ID.into()));
+    assert_eq!(fixture.vmt_balance(BUYER_ID, TICKET_ID), 1.into());
+    assert_eq!(fixture.claimable(BUYER_ID).await, U256::zero());
+}
+
+#[tokio::test]
+async fn event_cannot_be_cancelled_after_revenue_release() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.buy_primary(BUYER_ID, 1).await;
+    fixture.start_event().await;
+    fixture
+        .ticket(USER_ID)
+        .release_primary_revenue(EVENT_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    assert!(fixture
+        .ticket(USER_ID)
+        .cancel_event(EVENT_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn platform_fees_are_withdrawn_to_the_fee_recipient() {
+    let fixture = listed_ticket().await;
+    fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
         .await
         .unwrap();
 
-    let metadata = vec![Some(TokenMetadata {
-        title: Some(String::from("Sum 41 concert in Madrid 26/08/2022")),
-        description: Some(String::from(
-            "Sum 41 Madrid 26/08/2022 Ticket. Row 4. Seat 4.",
-        )),
-        media: Some(String::from("sum41.com")),
-        reference: Some(String::from("UNKNOWN")),
-    })];
-    // buy tickets
-    client
-        .buy_tickets(AMOUNT, metadata)
-        .send_recv(concert_id)
+    let platform_share = RESALE_PRICE / 100 * 5;
+    let concert_balance = fixture.concert_balance();
+
+    // Only the admin withdraws the platform fees
+    assert!(fixture
+        .ticket(SELLER_ID)
+        .withdraw_platform_fees(None)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    fixture
+        .ticket(USER_ID)
+        .withdraw_platform_fees(None)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    assert_eq!(fixture.concert_balance(), concert_balance - platform_share);
+    let platform = fixture
+        .ticket(USER_ID)
+        .get_platform_revenue(None)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(platform.accrued, U256::zero());
+    assert_eq!(platform.total, U256::from(platform_share));
+    let event_revenue = fixture
+        .ticket(USER_ID)
+        .get_event_platform_revenue(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(event_revenue, U256::from(platform_share));
+
+    assert!(fixture
+        .ticket(USER_ID)
+        .withdraw_platform_fees(None)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn financials_track_sales_and_the_floor_price() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 2).await;
+    fixture.buy_primary(BUYER_ID, 1).await;
+    fixture.approve(SELLER_ID);
+    fixture.list(SELLER_ID, TICKET_ID, RESALE_PRICE).await;
+    fixture
+        .list(SELLER_ID, TICKET_ID + 1, RESALE_PRICE * 2)
+        .await;
+
+    let financials = fixture
+        .ticket(USER_ID)
+        .get_event_financials(EVENT_ID)
+        .recv(fixture.concert_id)
         .await
+        .unwrap()
         .unwrap();
+    assert_eq!(financials.primary_revenue, U256::from(PRICE));
+    assert_eq!(financials.active_listings, 2);
+    assert_eq!(
+        financials.floor_listing_price,
+        Some(U256::from(RESALE_PRICE))
+    );
 
-    // check state
-    let state = client.get_storage().recv(concert_id).await.unwrap();
+    fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .unwrap();
 
-    assert_eq!(state.buyers, vec![USER_ID.into()]);
-    assert_eq!(state.tickets_left, U256::from(99));
-    assert_eq!(state.metadata[0].0, USER_ID.into());
-    let balance = get_balance(
-        program_space.system(),
-        &vmt_program,
-        USER_ID.into(),
-        TOKEN_ID,
+    let financials = fixture
+        .ticket(USER_ID)
+        .get_event_financials(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(financials.secondary_volume, U256::from(RESALE_PRICE));
+    assert_eq!(financials.resale_count, 1);
+    assert_eq!(
+        financials.organizer_royalties,
+        U256::from(RESALE_PRICE / 100 * 10)
+    );
+    assert_eq!(financials.active_listings, 1);
+    assert_eq!(
+        financials.floor_listing_price,
+        Some(U256::from(RESALE_PRICE * 2))
     );
-    assert_eq!(balance, 1.into());
-}
-
-#[tokio::test]
-async fn hold_concert() {
-    let system = System::new();
-    system.init_logger();
-    system.mint_to(USER_ID, 1_000_000_000_000_000);
-    let program_space = GTestRemoting::new(system, USER_ID.into());
-    let code_id = program_space
-        .system()
-        .submit_code_file("../target/wasm32-gear/release/concert.opt.wasm");
-
-    let concert_factory = Factory::new(program_space.clone());
-    let (vmt_id, vmt_program) = init_multitoken(program_space.system());
-    let concert_id = concert_factory
-        .new(USER_ID.into(), vmt_id)
-        .send_recv(code_id, "123")
-        .await
-        .unwrap();
-    grant_roles(program_space.system(), &vmt_program, concert_id);
-    let mut client = ConcertClient::new(program_space.clone());
-    // create
-    client
-        .create(
-            USER_ID.into(),
-            String::from("Sum 41"),
-            String::from("Sum 41 concert in Madrid. 26/08/2022"),
-            U256::from(100),
-            DATE,
-            TOKEN_ID,
+}
+
+#[tokio::test]
+async fn cancelled_offer_returns_the_escrow() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 1).await;
+    let expires_at = fixture.now() + 10 * MINUTE;
+    let concert_balance = fixture.concert_balance();
+
+    fixture
+        .market(BUYER_ID)
+        .make_offer(TICKET_ID, U256::from(PRICE), expires_at)
+        .with_value(PRICE)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(fixture.concert_balance(), concert_balance + PRICE);
+    let offers = fixture
+        .market(USER_ID)
+        .get_ticket_offers(TICKET_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(offers.len(), 1);
+    let offer_id = offers[0].offer_id;
+
+    // An expired offer can no longer be accepted, but its escrow can still be recovered
+    fixture.run_past(expires_at);
+    fixture.approve(SELLER_ID);
+    assert!(fixture
+        .market(SELLER_ID)
+        .accept_offer(offer_id)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    assert!(fixture
+        .market(SELLER_ID)
+        .cancel_offer(offer_id)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    fixture
+        .market(BUYER_ID)
+        .cancel_offer(offer_id)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    assert_eq!(fixture.concert_balance(), concert_balance);
+    let offer = fixture
+        .market(USER_ID)
+        .get_offer(offer_id)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(offer.is_none());
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(SELLER_ID.into()));
+}
+
+#[tokio::test]
+async fn accepted_offer_pays_the_seller_from_the_escrow() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 1).await;
+    let concert_balance = fixture.concert_balance();
+    fixture
+        .market(BUYER_ID)
+        .make_offer(TICKET_ID, U256::from(PRICE), fixture.now() + 10 * MINUTE)
+        .with_value(PRICE)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    let offers = fixture
+        .market(USER_ID)
+        .get_bidder_offers(BUYER_ID.into())
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    fixture.approve(SELLER_ID);
+    fixture
+        .market(SELLER_ID)
+        .accept_offer(offers[0].offer_id)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(BUYER_ID.into()));
+    assert_eq!(fixture.vmt_balance(BUYER_ID, TICKET_ID), 1.into());
+    assert_eq!(
+        fixture.claimable(SELLER_ID).await,
+        U256::from(PRICE / 100 * 85)
+    );
+    assert_eq!(fixture.concert_balance(), concert_balance + PRICE);
+}
+
+#[tokio::test]
+async fn outbid_bidder_is_credited_and_the_auction_settles() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 1).await;
+    fixture.approve(SELLER_ID);
+    let end_time = fixture.now() + 30 * MINUTE;
+    fixture
+        .market(SELLER_ID)
+        .create_auction(
+            TICKET_ID,
+            U256::from(PRICE),
+            U256::from(PRICE / 10),
+            end_time,
         )
-        .send_recv(concert_id)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    fixture
+        .market(BUYER_ID)
+        .place_bid(TICKET_ID, U256::from(PRICE))
+        .with_value(PRICE)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    // Below the minimum increment
+    assert!(fixture
+        .market(OTHER_ID)
+        .place_bid(TICKET_ID, U256::from(PRICE + 1))
+        .with_value(PRICE + 1)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    fixture
+        .market(OTHER_ID)
+        .place_bid(TICKET_ID, U256::from(PRICE * 2))
+        .with_value(PRICE * 2)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    // The outbid bid moves to the claimable balance of its bidder
+    assert_eq!(fixture.claimable(BUYER_ID).await, U256::from(PRICE));
+
+    assert!(fixture
+        .market(USER_ID)
+        .settle_auction(TICKET_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    fixture.run_past(end_time);
+    fixture
+        .market(USER_ID)
+        .settle_auction(TICKET_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(OTHER_ID.into()));
+    assert_eq!(fixture.vmt_balance(OTHER_ID, TICKET_ID), 1.into());
+    assert_eq!(
+        fixture.claimable(SELLER_ID).await,
+        U256::from(PRICE * 2 / 100 * 85)
+    );
+    let auction = fixture
+        .market(USER_ID)
+        .get_auction(TICKET_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(auction.is_none());
+}
+
+#[tokio::test]
+async fn dutch_listing_sells_at_the_current_price_and_sets_the_floor() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 2).await;
+    fixture.approve(SELLER_ID);
+    let ends_at = fixture.now() + 20 * MINUTE;
+    for ticket_id in [TICKET_ID, TICKET_ID + 1] {
+        fixture
+            .market(SELLER_ID)
+            .list_ticket_dutch(
+                ticket_id,
+                U256::from(RESALE_PRICE),
+                U256::from(PRICE),
+                ends_at,
+            )
+            .send_recv(fixture.concert_id)
+            .await
+            .unwrap();
+    }
+
+    // Halfway through the floor is the declining price, between the start and the end
+    fixture.run_past(ends_at - 10 * MINUTE);
+    let floor = fixture
+        .ticket(USER_ID)
+        .get_event_financials(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .floor_listing_price
+        .unwrap();
+    assert!(floor > U256::from(PRICE) && floor < U256::from(RESALE_PRICE));
+
+    // The excess over the current price is returned
+    let concert_balance = fixture.concert_balance();
+    fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .unwrap();
+    let seller_share = fixture.claimable(SELLER_ID).await;
+    assert!(seller_share > U256::from(PRICE / 100 * 85));
+    assert!(seller_share < U256::from(RESALE_PRICE / 100 * 85));
+    assert!(fixture.concert_balance() < concert_balance + RESALE_PRICE);
+
+    // Once the decline ends the remaining listing sits at its floor price
+    fixture.run_past(ends_at);
+    let financials = fixture
+        .ticket(USER_ID)
+        .get_event_financials(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(financials.floor_listing_price, Some(U256::from(PRICE)));
+}
+
+#[tokio::test]
+async fn expired_listing_cannot_be_bought_and_is_swept() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 1).await;
+    fixture.approve(SELLER_ID);
+    let expires_at = fixture.now() + 10 * MINUTE;
+    fixture
+        .market(SELLER_ID)
+        .list_ticket(TICKET_ID, U256::from(RESALE_PRICE), Some(expires_at), None)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    // Nothing to sweep before the expiry
+    let swept = fixture
+        .market(OTHER_ID)
+        .sweep_expired_listings(10)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(swept, 0);
+
+    fixture.run_past(expires_at);
+    assert!(fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .is_err());
+
+    // Anyone can sweep it
+    let swept = fixture
+        .market(OTHER_ID)
+        .sweep_expired_listings(10)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(swept, 1);
+    let listing = fixture
+        .market(USER_ID)
+        .get_listing(TICKET_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(listing.is_none());
+}
+
+#[tokio::test]
+async fn seller_updates_the_listing_price() {
+    let fixture = listed_ticket().await;
+    let new_price = RESALE_PRICE + PRICE;
+
+    assert!(fixture
+        .market(OTHER_ID)
+        .update_listing_price(TICKET_ID, U256::from(new_price))
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    fixture
+        .market(SELLER_ID)
+        .update_listing_price(TICKET_ID, U256::from(new_price))
+        .send_recv(fixture.concert_id)
         .await
         .unwrap();
 
-    let metadata = vec![Some(TokenMetadata {
-        title: Some(String::from("Sum 41 concert in Madrid 26/08/2022")),
-        description: Some(String::from(
-            "Sum 41 Madrid 26/08/2022 Ticket. Row 4. Seat 4.",
-        )),
-        media: Some(String::from("sum41.com")),
-        reference: Some(String::from("UNKNOWN")),
-    })];
-    // buy tickets
-    client
-        .buy_tickets(AMOUNT, metadata)
-        .send_recv(concert_id)
+    let listing = fixture
+        .market(USER_ID)
+        .get_listing(TICKET_ID)
+        .recv(fixture.concert_id)
         .await
+        .unwrap()
         .unwrap();
+    assert_eq!(listing.price, U256::from(new_price));
 
-    // hold concert
-    client.hold_concert().send_recv(concert_id).await.unwrap();
+    // The old price no longer buys the ticket
+    assert!(fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .is_err());
+    fixture.buy(BUYER_ID, TICKET_ID, new_price).await.unwrap();
+    assert_eq!(
+        fixture.claimable(SELLER_ID).await,
+        U256::from(new_price / 100 * 85)
+    );
+}
+
+#[tokio::test]
+async fn batch_purchase_buys_every_listing() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 2).await;
+    fixture.approve(SELLER_ID);
+    fixture.list(SELLER_ID, TICKET_ID, RESALE_PRICE).await;
+    fixture.list(SELLER_ID, TICKET_ID + 1, PRICE).await;
 
-    // check state
-    let state = client.get_storage().recv(concert_id).await.unwrap();
+    fixture
+        .market(BUYER_ID)
+        .buy_tickets(BUYER_ID.into(), vec![TICKET_ID, TICKET_ID + 1])
+        .with_value(RESALE_PRICE + PRICE)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
 
-    assert!(!state.running);
-    let balance = get_balance(
-        program_space.system(),
-        &vmt_program,
-        USER_ID.into(),
-        TOKEN_ID,
+    for ticket_id in [TICKET_ID, TICKET_ID + 1] {
+        assert_eq!(fixture.owner(ticket_id).await, Some(BUYER_ID.into()));
+        assert_eq!(fixture.vmt_balance(BUYER_ID, ticket_id), 1.into());
+    }
+    assert_eq!(
+        fixture.claimable(SELLER_ID).await,
+        U256::from((RESALE_PRICE + PRICE) / 100 * 85)
     );
-    assert_eq!(balance, 0.into());
-    let balance = get_balance(
-        program_space.system(),
-        &vmt_program,
-        USER_ID.into(),
-        TOKEN_ID + 1,
+}
+
+#[tokio::test]
+async fn failed_batch_transfer_sells_nothing() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 2).await;
+    fixture.approve(SELLER_ID);
+    fixture.list(SELLER_ID, TICKET_ID, RESALE_PRICE).await;
+    fixture.list(SELLER_ID, TICKET_ID + 1, RESALE_PRICE).await;
+
+    // The seller moves the second NFT away in VMT, so the batch transfer fails
+    fixture.send_vmt(
+        SELLER_ID,
+        vmt_io::TransferFrom::encode_params_with_prefix(
+            "Vmt",
+            SELLER_ID.into(),
+            OTHER_ID.into(),
+            TICKET_ID + 1,
+            U256::one(),
+        ),
     );
-    assert_eq!(balance, 1.into());
+    let concert_balance = fixture.concert_balance();
+
+    fixture
+        .market(BUYER_ID)
+        .buy_tickets(BUYER_ID.into(), vec![TICKET_ID, TICKET_ID + 1])
+        .with_value(RESALE_PRICE * 2)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    // Neither ticket was sold, both listings are back and the payment was returned
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(SELLER_ID.into()));
+    assert_eq!(fixture.vmt_balance(SELLER_ID, TICKET_ID), 1.into());
+    assert_eq!(fixture.vmt_balance(BUYER_ID, TICKET_ID), 0.into());
+    for ticket_id in [TICKET_ID, TICKET_ID + 1] {
+        let listing = fixture
+            .market(USER_ID)
+            .get_listing(ticket_id)
+            .recv(fixture.concert_id)
+            .await
+            .unwrap();
+        assert!(listing.is_some());
+    }
+    assert_eq!(fixture.claimable(SELLER_ID).await, U256::zero());
+    assert_eq!(fixture.concert_balance(), concert_balance);
+}
+
+#[tokio::test]
+async fn listings_are_sorted_and_paginated() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 3).await;
+    for (ticket_id, price) in [
+        (TICKET_ID, PRICE * 3),
+        (TICKET_ID + 1, PRICE),
+        (TICKET_ID + 2, PRICE * 2),
+    ] {
+        fixture.list(SELLER_ID, ticket_id, price).await;
+    }
+
+    let page = fixture
+        .market(USER_ID)
+        .query_listings(no_filter(), ListingSort::PriceAsc, 0, 2)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        ticket_ids(&page.listings),
+        vec![TICKET_ID + 1, TICKET_ID + 2]
+    );
+    assert_eq!(page.next_cursor, Some(2));
+    assert_eq!(page.total, 3);
+
+    let page = fixture
+        .market(USER_ID)
+        .query_listings(no_filter(), ListingSort::PriceAsc, 2, 2)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(ticket_ids(&page.listings), vec![TICKET_ID]);
+    assert_eq!(page.next_cursor, None);
+
+    // An empty page has no continuation
+    let page = fixture
+        .market(USER_ID)
+        .query_listings(no_filter(), ListingSort::PriceDesc, 0, 0)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(page.listings.is_empty());
+    assert_eq!(page.next_cursor, None);
+    assert_eq!(page.total, 3);
+
+    let filter = ListingFilter {
+        max_price: Some(U256::from(PRICE * 2)),
+        ..no_filter()
+    };
+    let page = fixture
+        .market(USER_ID)
+        .query_listings(filter, ListingSort::PriceDesc, 0, 10)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        ticket_ids(&page.listings),
+        vec![TICKET_ID + 2, TICKET_ID + 1]
+    );
+}
+
+#[tokio::test]
+async fn listings_are_indexed_by_event_and_seller() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 1).await;
+    fixture.mint(EVENT_ID + 1, SELLER_ID, 1).await;
+    fixture.mint(EVENT_ID, BUYER_ID, 1).await;
+    fixture.list(SELLER_ID, TICKET_ID, RESALE_PRICE).await;
+    fixture.list(SELLER_ID, TICKET_ID + 1, RESALE_PRICE).await;
+    fixture.list(BUYER_ID, TICKET_ID + 2, RESALE_PRICE).await;
+
+    let listings = fixture
+        .market(USER_ID)
+        .get_event_listings(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    let mut ids = ticket_ids(&listings);
+    ids.sort();
+    assert_eq!(ids, vec![TICKET_ID, TICKET_ID + 2]);
+
+    let listings = fixture
+        .market(USER_ID)
+        .get_seller_listings(SELLER_ID.into())
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    let mut ids = ticket_ids(&listings);
+    ids.sort();
+    assert_eq!(ids, vec![TICKET_ID, TICKET_ID + 1]);
+
+    let filter = ListingFilter {
+        event_id: Some(EVENT_ID),
+        seller: Some(SELLER_ID.into()),
+        ..no_filter()
+    };
+    let page = fixture
+        .market(USER_ID)
+        .query_listings(filter, ListingSort::ListedAtAsc, 0, 10)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(ticket_ids(&page.listings), vec![TICKET_ID]);
+    assert_eq!(page.total, 1);
+
+    // A cancelled listing leaves both indexes
+    fixture
+        .market(SELLER_ID)
+        .cancel_listing(TICKET_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    let listings = fixture
+        .market(USER_ID)
+        .get_seller_listings(SELLER_ID.into())
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(ticket_ids(&listings), vec![TICKET_ID + 1]);
+    let listings = fixture
+        .market(USER_ID)
+        .get_event_listings(EVENT_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(ticket_ids(&listings), vec![TICKET_ID + 2]);
+}
+
+#[tokio::test]
+async fn used_ticket_is_delisted() {
+    let fixture = listed_ticket().await;
+
+    fixture
+        .ticket(USER_ID)
+        .mark_ticket_used(TICKET_ID)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    let listing = fixture
+        .market(USER_ID)
+        .get_listing(TICKET_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(listing.is_none());
+    assert!(fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn inactive_event_is_delisted_and_rejects_resales() {
+    let fixture = listed_ticket().await;
+
+    fixture
+        .ticket(USER_ID)
+        .set_event_active(EVENT_ID, false)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    let listing = fixture
+        .market(USER_ID)
+        .get_listing(TICKET_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(listing.is_none());
+
+    // Neither a new listing nor a direct resale goes through while the event is inactive
+    assert!(fixture
+        .market(SELLER_ID)
+        .list_ticket(TICKET_ID, U256::from(RESALE_PRICE), None, None)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    assert!(fixture
+        .ticket(SELLER_ID)
+        .resell_ticket(TICKET_ID, BUYER_ID.into(), U256::from(RESALE_PRICE))
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(SELLER_ID.into()));
+
+    // Reactivated, the ticket can be listed again
+    fixture
+        .ticket(USER_ID)
+        .set_event_active(EVENT_ID, true)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    fixture.list(SELLER_ID, TICKET_ID, RESALE_PRICE).await;
+}
+
+#[tokio::test]
+async fn face_value_listing_is_matched_with_the_waitlist() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 1).await;
+    fixture.approve(SELLER_ID);
+    fixture
+        .ticket(USER_ID)
+        .set_fan_exchange(EVENT_ID, true)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    fixture
+        .market(BUYER_ID)
+        .join_waitlist(EVENT_ID, None)
+        .with_value(PRICE)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    let waitlist = fixture
+        .market(USER_ID)
+        .get_waitlist(EVENT_ID, None)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(waitlist.len(), 1);
+    assert_eq!(waitlist[0].buyer, BUYER_ID.into());
+
+    // Listing at face value sells the ticket to the first in line with their deposit
+    fixture.list(SELLER_ID, TICKET_ID, PRICE).await;
+
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(BUYER_ID.into()));
+    assert_eq!(fixture.vmt_balance(BUYER_ID, TICKET_ID), 1.into());
+    let listing = fixture
+        .market(USER_ID)
+        .get_listing(TICKET_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(listing.is_none());
+    let waitlist = fixture
+        .market(USER_ID)
+        .get_waitlist(EVENT_ID, None)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(waitlist.is_empty());
+    assert_eq!(
+        fixture.claimable(SELLER_ID).await,
+        U256::from(PRICE / 100 * 85)
+    );
+}
+
+#[tokio::test]
+async fn reserved_listing_is_only_sold_to_its_buyer() {
+    let fixture = Fixture::new().await;
+    fixture.create_event().await;
+    fixture.mint(EVENT_ID, SELLER_ID, 1).await;
+    fixture.approve(SELLER_ID);
+    fixture
+        .market(SELLER_ID)
+        .list_ticket(
+            TICKET_ID,
+            U256::from(RESALE_PRICE),
+            None,
+            Some(BUYER_ID.into()),
+        )
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+
+    // The listing is visible by id with its reservation, but not among the public listings
+    let listing = fixture
+        .market(OTHER_ID)
+        .get_listing(TICKET_ID)
+        .recv(fixture.concert_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(listing.reserved_for, Some(BUYER_ID.into()));
+    let listings = fixture
+        .market(USER_ID)
+        .get_all_listings()
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert!(listings.is_empty());
+
+    assert!(fixture
+        .buy(OTHER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .is_err());
+    fixture
+        .buy(BUYER_ID, TICKET_ID, RESALE_PRICE)
+        .await
+        .unwrap();
+    assert_eq!(fixture.owner(TICKET_ID).await, Some(BUYER_ID.into()));
+}
+
+#[tokio::test]
+async fn zone_capacity_limits_the_primary_sale() {
+    let fixture = Fixture::new().await;
+    fixture
+        .create_event_with_zones(vec![
+            ZoneConfig {
+                name: "VIP".to_string(),
+                capacity: U256::one(),
+                face_price: Some(U256::from(PRICE * 2)),
+            },
+            ZoneConfig {
+                name: "General".to_string(),
+                capacity: U256::from(99),
+                face_price: Some(U256::from(PRICE)),
+            },
+        ])
+        .await;
+
+    // The zone price applies, not the event price
+    assert!(fixture
+        .ticket(BUYER_ID)
+        .buy_primary(EVENT_ID, Some("VIP".to_string()), U256::one(), vec![])
+        .with_value(PRICE)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    fixture
+        .ticket(BUYER_ID)
+        .buy_primary(EVENT_ID, Some("VIP".to_string()), U256::one(), vec![])
+        .with_value(PRICE * 2)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        fixture.ticket_info(TICKET_ID).await.unwrap().zone,
+        Some("VIP".to_string())
+    );
+
+    // The zone is sold out while the rest of the event is not
+    assert!(fixture
+        .ticket(OTHER_ID)
+        .buy_primary(EVENT_ID, Some("VIP".to_string()), U256::one(), vec![])
+        .with_value(PRICE * 2)
+        .send_recv(fixture.concert_id)
+        .await
+        .is_err());
+    let availability = fixture
+        .ticket(USER_ID)
+        .get_zone_availability(EVENT_ID, "VIP".to_string())
+        .recv(fixture.concert_id)
+        .await
+        .unwrap();
+    assert_eq!(availability, Some(U256::zero()));
+    fixture
+        .ticket(OTHER_ID)
+        .buy_primary(EVENT_ID, Some("General".to_string()), U256::one(), vec![])
+        .with_value(PRICE)
+        .send_recv(fixture.concert_id)
+        .await
+        .unwrap();
 }
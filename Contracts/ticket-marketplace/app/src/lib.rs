@@ -3,7 +3,7 @@
 use core::fmt::Debug;
 use gstd::{ext, format, msg};
 use sails_rs::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     prelude::*,
 };
 
@@ -25,11 +25,52 @@ pub struct Storage {
     
     // Listados activos: ticket_id -> Listing
     listings: HashMap<U256, Listing>,
+    event_listings: HashMap<U256, HashSet<U256>>, // event_id -> {ticket_id} listados
+    seller_listings: HashMap<ActorId, HashSet<U256>>, // vendedor -> {ticket_id} listados
     
     // Reentrancy guard
     locked: bool,
 }
 
+impl Storage {
+    /// Agrega un listado activo y sus índices por evento y por vendedor
+    fn insert_listing(&mut self, listing: Listing) {
+        self.event_listings
+            .entry(listing.event_id)
+            .or_default()
+            .insert(listing.ticket_id);
+        self.seller_listings
+            .entry(listing.seller)
+            .or_default()
+            .insert(listing.ticket_id);
+        self.listings.insert(listing.ticket_id, listing);
+    }
+    
+    /// Remueve un listado activo y sus índices
+    fn remove_listing(&mut self, ticket_id: &U256) -> Option<Listing> {
+        let listing = self.listings.remove(ticket_id)?;
+        if let Some(ids) = self.event_listings.get_mut(&listing.event_id) {
+            ids.remove(ticket_id);
+            if ids.is_empty() {
+                self.event_listings.remove(&listing.event_id);
+            }
+        }
+        if let Some(ids) = self.seller_listings.get_mut(&listing.seller) {
+            ids.remove(ticket_id);
+            if ids.is_empty() {
+                self.seller_listings.remove(&listing.seller);
+            }
+        }
+        Some(listing)
+    }
+    
+    /// Listados activos de un conjunto de IDs del índice (vacío si no hay índice)
+    fn listings_in(&self, ids: Option<&HashSet<U256>>) -> Vec<Listing> {
+        ids.map(|ids| ids.iter().filter_map(|id| self.listings.get(id).cloned()).collect())
+            .unwrap_or_default()
+    }
+}
+
 /// Información de un listado activo
 #[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
 #[codec(crate = sails_rs::scale_codec)]
@@ -52,6 +93,8 @@ pub struct TicketInfo {
     pub event_id: U256,
     pub current_owner: ActorId,
     pub used: bool,
+    pub zone: Option<String>,
+    pub purchase_price: U256, // 0 si se vendió fuera del contrato de Tickets
 }
 
 /// Estructuras del contrato de Tickets (para comunicación)
@@ -63,10 +106,12 @@ pub struct Ticket {
     pub ticket_id: U256,
     pub event_id: U256,
     pub zone: Option<String>,
+    pub seat: Option<SeatTicket>,
     pub original_buyer: ActorId,
     pub current_owner: ActorId,
     pub used: bool,
     pub minted_at: u64,
+    pub purchase_price: U256,
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
@@ -82,6 +127,42 @@ pub struct EventConfig {
     pub resale_config: ResaleConfigTicket,
     pub commission_config: CommissionConfigTicket,
     pub active: bool,
+    pub payment_token: Option<ActorId>,
+    pub primary_price: Option<U256>,
+    pub zones: Vec<EventZoneTicket>,
+    pub cancelled: bool,
+    pub wallet_limits: WalletLimitsTicket,
+    pub fan_exchange: bool,
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct EventZoneTicket {
+    pub zone_id: u32,
+    pub name: String,
+    pub capacity: U256,
+    pub face_price: Option<U256>,
+    pub minted: U256,
+    pub seats: Vec<SeatTicket>,
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct SeatTicket {
+    pub section: String,
+    pub row: String,
+    pub number: u32,
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct WalletLimitsTicket {
+    pub max_primary_purchases: Option<u32>,
+    pub max_holdings: Option<u32>,
+    pub max_resales: Option<u32>,
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
@@ -90,6 +171,8 @@ pub struct EventConfig {
 pub struct ResaleConfigTicket {
     pub enabled: bool,
     pub max_price: Option<U256>,
+    pub min_price: Option<U256>,
+    pub max_markup_bps: Option<u32>,
     pub resale_start_time: Option<u64>,
     pub resale_end_time: Option<u64>,
 }
@@ -101,6 +184,7 @@ pub struct CommissionConfigTicket {
     pub seller_percentage: u16,
     pub organizer_percentage: u16,
     pub platform_percentage: u16,
+    pub beneficiaries: Vec<(ActorId, u16)>,
 }
 
 /// Información del evento desde el contrato de Tickets
@@ -112,11 +196,62 @@ pub struct EventInfo {
     pub organizer: ActorId,
     pub resale_enabled: bool,
     pub max_price: Option<U256>,
+    pub min_price: Option<U256>,
+    pub max_markup_bps: Option<u32>,
+    pub primary_price: Option<U256>,
+    pub zone_prices: Vec<(String, Option<U256>)>, // Precio de lista por zona (vacío = evento sin zonas)
     pub resale_start_time: Option<u64>,
     pub resale_end_time: Option<u64>,
     pub seller_percentage: u16,
     pub organizer_percentage: u16,
     pub platform_percentage: u16,
+    pub beneficiaries: Vec<(ActorId, u16)>, // Regalías adicionales en basis points
+    pub cancelled: bool,
+}
+
+impl EventInfo {
+    /// Valida un precio de reventa de `ticket` contra los límites del evento:
+    /// precio máximo, precio mínimo y recargo máximo sobre el precio de compra
+    /// (si no se registró on-chain, el recargo se mide contra el precio de lista de su zona)
+    fn check_resale_price(&self, price: U256, ticket: &TicketInfo) -> Result<(), MarketplaceError> {
+        if let Some(max_price) = self.max_price {
+            if price > max_price {
+                return Err(MarketplaceError::PriceExceedsMaximum);
+            }
+        }
+        if let Some(min_price) = self.min_price {
+            if price < min_price {
+                return Err(MarketplaceError::PriceBelowMinimum);
+            }
+        }
+        if let Some(max_markup_bps) = self.max_markup_bps {
+            let base = if ticket.purchase_price.is_zero() {
+                self.primary_price_for(&ticket.zone)
+            } else {
+                Some(ticket.purchase_price)
+            };
+            if let Some(base) = base {
+                let cap = base * (U256::from(BASIS_POINTS) + U256::from(max_markup_bps))
+                    / U256::from(BASIS_POINTS);
+                if price > cap {
+                    return Err(MarketplaceError::MarkupExceedsMaximum);
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Precio de lista de una zona; sin tabla de zonas aplica `primary_price`
+    fn primary_price_for(&self, zone: &Option<String>) -> Option<U256> {
+        match zone {
+            Some(name) if !self.zone_prices.is_empty() => self
+                .zone_prices
+                .iter()
+                .find(|(zone_name, _)| zone_name == name)
+                .and_then(|(_, face_price)| *face_price),
+            _ => self.primary_price,
+        }
+    }
 }
 
 /// Mensajes para comunicarse con el contrato de Tickets
@@ -164,6 +299,8 @@ pub enum Event {
         seller_share: U256,
         organizer_share: U256,
         platform_share: U256,
+        beneficiary_shares: Vec<(ActorId, U256)>, // Regalías de beneficiarios
+        rounding_remainder: U256, // Resto del redondeo, incluido en seller_share
     },
     /// Listado cancelado
     ListingCancelled {
@@ -171,6 +308,14 @@ pub enum Event {
         event_id: U256,
         seller: ActorId,
     },
+    /// Precio de un listado actualizado
+    ListingPriceUpdated {
+        ticket_id: U256,
+        event_id: U256,
+        seller: ActorId,
+        old_price: U256,
+        new_price: U256,
+    },
 }
 
 /// Errores del Marketplace
@@ -184,12 +329,15 @@ pub enum MarketplaceError {
     TicketAlreadyUsed,
     ResaleDisabled,
     PriceExceedsMaximum,
+    PriceBelowMinimum,
+    MarkupExceedsMaximum,
     ResaleWindowClosed,
     InvalidPrice,
     InvalidInput,
     ReentrancyDetected,
     PurchaseFailed,
     TransferFailed,
+    EventCancelled,
 }
 
 static mut STORAGE: Option<Storage> = None;
@@ -266,6 +414,8 @@ impl MarketplaceService {
                                     event_id: ticket.event_id,
                                     current_owner: ticket.current_owner,
                                     used: ticket.used,
+                                    zone: ticket.zone,
+                                    purchase_price: ticket.purchase_price,
                                 })
                             }
                             None => None,
@@ -302,11 +452,21 @@ impl MarketplaceService {
                                     organizer: event_config.organizer,
                                     resale_enabled: event_config.resale_config.enabled,
                                     max_price: event_config.resale_config.max_price,
+                                    min_price: event_config.resale_config.min_price,
+                                    max_markup_bps: event_config.resale_config.max_markup_bps,
+                                    primary_price: event_config.primary_price,
+                                    zone_prices: event_config
+                                        .zones
+                                        .into_iter()
+                                        .map(|zone| (zone.name, zone.face_price))
+                                        .collect(),
                                     resale_start_time: event_config.resale_config.resale_start_time,
                                     resale_end_time: event_config.resale_config.resale_end_time,
                                     seller_percentage: event_config.commission_config.seller_percentage,
                                     organizer_percentage: event_config.commission_config.organizer_percentage,
                                     platform_percentage: event_config.commission_config.platform_percentage,
+                                    beneficiaries: event_config.commission_config.beneficiaries,
+                                    cancelled: event_config.cancelled,
                                 })
                             }
                             None => None,
@@ -319,8 +479,8 @@ impl MarketplaceService {
         }
     }
     
-    /// Valida que el ticket puede ser listado
-    async fn validate_ticket_for_listing(&self, ticket_id: U256, seller: ActorId) -> Result<EventInfo, MarketplaceError> {
+    /// Valida que el ticket puede ser listado a `price`
+    async fn validate_ticket_for_listing(&self, ticket_id: U256, seller: ActorId, price: U256) -> Result<EventInfo, MarketplaceError> {
         // Obtener información del ticket
         let ticket_info = self.get_ticket_info(ticket_id).await;
         if ticket_info.is_none() {
@@ -345,6 +505,11 @@ impl MarketplaceService {
         }
         let event_info = event_info.unwrap();
         
+        // Un evento cancelado no admite reventas: sus tickets se reembolsan
+        if event_info.cancelled {
+            return Err(MarketplaceError::EventCancelled);
+        }
+        
         // Validar que la reventa esté habilitada
         if !event_info.resale_enabled {
             return Err(MarketplaceError::ResaleDisabled);
@@ -363,6 +528,9 @@ impl MarketplaceService {
             }
         }
         
+        // Validar límites de precio (máximo, mínimo y recargo)
+        event_info.check_resale_price(price, &ticket_info)?;
+        
         Ok(event_info)
     }
     
@@ -448,21 +616,10 @@ impl MarketplaceService {
             panic(MarketplaceError::TicketAlreadyListed);
         }
         
-        // Validar ticket y obtener info del evento
-        let event_info = match self.validate_ticket_for_listing(ticket_id, seller).await {
-            Ok(info) => info,
-            Err(e) => {
-                self.unlock();
-                panic(e);
-            }
-        };
-        
-        // Validar precio máximo
-        if let Some(max_price) = event_info.max_price {
-            if price > max_price {
-                self.unlock();
-                panic(MarketplaceError::PriceExceedsMaximum);
-            }
+        // Validar ticket y sus límites de precio
+        if let Err(e) = self.validate_ticket_for_listing(ticket_id, seller, price).await {
+            self.unlock();
+            panic(e);
         }
         
         // Obtener información del ticket para el event_id
@@ -482,7 +639,7 @@ impl MarketplaceService {
             event_id: ticket_info.event_id,
         };
         
-        storage.listings.insert(ticket_id, listing.clone());
+        storage.insert_listing(listing);
         
         self.emit_event(Event::TicketListed {
             ticket_id,
@@ -523,7 +680,8 @@ impl MarketplaceService {
         }
         
         // Validar que el ticket todavía es válido para venta
-        let event_info = match self.validate_ticket_for_listing(ticket_id, listing.seller).await {
+        // (incluye el precio del listado vs los límites actuales del evento)
+        let event_info = match self.validate_ticket_for_listing(ticket_id, listing.seller, listing.price).await {
             Ok(info) => info,
             Err(e) => {
                 self.unlock();
@@ -531,32 +689,29 @@ impl MarketplaceService {
             }
         };
         
-        // Validar precio del listado vs precio máximo actual
-        if let Some(max_price) = event_info.max_price {
-            if listing.price > max_price {
-                self.unlock();
-                panic(MarketplaceError::PriceExceedsMaximum);
-            }
-        }
-        
         // Remover listado antes de procesar (previene doble compra)
-        storage.listings.remove(&ticket_id);
+        storage.remove_listing(&ticket_id);
         
         // Calcular comisiones
-        let seller_share = (listing.price.as_u128() * event_info.seller_percentage as u128) 
-            / BASIS_POINTS as u128;
-        let organizer_share = (listing.price.as_u128() * event_info.organizer_percentage as u128) 
+        // Organizador y plataforma se redondean hacia abajo; el resto del redondeo va al vendedor
+        let price_u128 = listing.price.as_u128();
+        let organizer_share = (price_u128 * event_info.organizer_percentage as u128) 
             / BASIS_POINTS as u128;
-        let platform_share = (listing.price.as_u128() * event_info.platform_percentage as u128) 
+        let platform_share = (price_u128 * event_info.platform_percentage as u128) 
             / BASIS_POINTS as u128;
-        
-        // Validar que la suma sea correcta
-        let total_distributed = seller_share + organizer_share + platform_share;
-        let price_u128 = listing.price.as_u128();
-        if total_distributed > price_u128 {
+        let beneficiary_shares: Vec<(ActorId, u128)> = event_info
+            .beneficiaries
+            .iter()
+            .map(|(beneficiary, bps)| (*beneficiary, (price_u128 * *bps as u128) / BASIS_POINTS as u128))
+            .collect();
+        let beneficiaries_total: u128 = beneficiary_shares.iter().map(|(_, amount)| *amount).sum();
+        if organizer_share + platform_share + beneficiaries_total > price_u128 {
             self.unlock();
             panic(MarketplaceError::InvalidPrice);
         }
+        let seller_share = price_u128 - organizer_share - platform_share - beneficiaries_total;
+        let rounding_remainder = seller_share
+            - (price_u128 * event_info.seller_percentage as u128) / BASIS_POINTS as u128;
         
         // Transferir pagos
         // Nota: En producción, esto se haría con msg::send o similar
@@ -567,7 +722,7 @@ impl MarketplaceService {
             Ok(_) => {},
             Err(e) => {
                 // Revertir: volver a agregar el listado
-                storage.listings.insert(ticket_id, listing.clone());
+                storage.insert_listing(listing.clone());
                 self.unlock();
                 panic(e);
             }
@@ -582,6 +737,11 @@ impl MarketplaceService {
             seller_share: U256::from(seller_share),
             organizer_share: U256::from(organizer_share),
             platform_share: U256::from(platform_share),
+            beneficiary_shares: beneficiary_shares
+                .into_iter()
+                .map(|(beneficiary, amount)| (beneficiary, U256::from(amount)))
+                .collect(),
+            rounding_remainder: U256::from(rounding_remainder),
         })
         .expect("Failed to emit TicketSold");
         
@@ -609,7 +769,7 @@ impl MarketplaceService {
         
         // Remover listado
         let event_id = listing.event_id;
-        storage.listings.remove(&ticket_id);
+        storage.remove_listing(&ticket_id);
         
         self.emit_event(Event::ListingCancelled {
             ticket_id,
@@ -619,6 +779,61 @@ impl MarketplaceService {
         .expect("Failed to emit ListingCancelled");
     }
     
+    /// Cambiar el precio de un listado activo
+    /// Conserva la fecha original del listado
+    #[export]
+    pub async fn update_listing_price(&mut self, ticket_id: U256, new_price: U256) {
+        self.non_reentrant();
+        
+        if new_price == U256::zero() {
+            self.unlock();
+            panic(MarketplaceError::InvalidPrice);
+        }
+        
+        let seller = msg::source();
+        
+        let storage = self.get_mut();
+        
+        // Obtener listado
+        let listing = storage.listings.get(&ticket_id);
+        if listing.is_none() {
+            self.unlock();
+            panic(MarketplaceError::ListingNotFound);
+        }
+        let listing = listing.unwrap().clone();
+        
+        // Validar propiedad del listado
+        if listing.seller != seller {
+            self.unlock();
+            panic(MarketplaceError::TicketNotOwned);
+        }
+        
+        // Validar ticket, ventana de reventa y límites de precio
+        if let Err(e) = self.validate_ticket_for_listing(ticket_id, seller, new_price).await {
+            self.unlock();
+            panic(e);
+        }
+        
+        // El listado pudo cambiar durante las consultas al contrato de Tickets
+        let listing_mut = storage.listings.get_mut(&ticket_id);
+        if listing_mut.is_none() {
+            self.unlock();
+            panic(MarketplaceError::ListingNotFound);
+        }
+        listing_mut.unwrap().price = new_price;
+        
+        self.emit_event(Event::ListingPriceUpdated {
+            ticket_id,
+            event_id: listing.event_id,
+            seller,
+            old_price: listing.price,
+            new_price,
+        })
+        .expect("Failed to emit ListingPriceUpdated");
+        
+        self.unlock();
+    }
+    
     /// Obtener información de un listado
     #[export]
     pub fn get_listing(&self, ticket_id: U256) -> Option<Listing> {
@@ -634,12 +849,15 @@ impl MarketplaceService {
     /// Obtener listados de un vendedor
     #[export]
     pub fn get_seller_listings(&self, seller: ActorId) -> Vec<Listing> {
-        self.get()
-            .listings
-            .values()
-            .filter(|listing| listing.seller == seller)
-            .cloned()
-            .collect()
+        let storage = self.get();
+        storage.listings_in(storage.seller_listings.get(&seller))
+    }
+    
+    /// Obtener listados activos de un evento
+    #[export]
+    pub fn get_event_listings(&self, event_id: U256) -> Vec<Listing> {
+        let storage = self.get();
+        storage.listings_in(storage.event_listings.get(&event_id))
     }
     
     /// Obtener estado del contrato